@@ -0,0 +1,553 @@
+//! Verifies several XP seeds at once, each seed gets a 64 bit lane and the first slot gets
+//! computed for every lane together. Lanes are 2 wide with SSE2 and wasm simd128 and 4 wide with
+//! AVX2, everything else goes through a one lane scalar version of the same code
+use crate::utils::{shows, slot_level, SimpleRandom, ADDEND, MASK, MULT};
+
+/// Most seeds `verify_seeds` looks at, one bit of the result for each
+pub const MAX_BATCH: usize = 16;
+
+/// Everything about an input that doesn't depend on the seed
+struct Check {
+    info: (i32, i32, i32, i32),
+    shelves: i32,
+    bound: u64,
+    /// `n * magic >> shift` is `n / bound` for any `n` below 2^31
+    magic: u64,
+    shift: i32,
+    power_of_two: bool,
+    /// Lowest and highest sum of both rolls that give the right level for each slot, the level
+    /// only goes up with the rolls so it's a single range
+    sums: [(u32, u32); 3],
+}
+
+impl Check {
+    /// `None` if some slot can't show its level at all
+    fn new(info: (i32, i32, i32, i32)) -> Option<Self> {
+        let (shelves, slot1, slot2, slot3) = info;
+        let bound = shelves as u64 + 1;
+        let bits = 64 - (bound - 1).leading_zeros() as i32;
+        let mut sums = [(0, 0); 3];
+        for (slot, level) in [slot1, slot2, slot3].into_iter().enumerate() {
+            let mut matching = (0..=7 + shelves as u32).filter(|&x| {
+                shows(
+                    slot_level(slot as i32, x as i32 + 1 + (shelves >> 1), shelves),
+                    level,
+                )
+            });
+            let lo = matching.next()?;
+            sums[slot] = (lo, matching.next_back().unwrap_or(lo));
+        }
+        Some(Check {
+            info,
+            shelves,
+            bound,
+            magic: (1u64 << (31 + bits)).div_ceil(bound),
+            shift: 31 + bits,
+            power_of_two: bound & (bound - 1) == 0,
+            sums,
+        })
+    }
+}
+
+/// Ways of running the lanes, not every one of them exists on every target
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Backend {
+    Scalar,
+    Sse2,
+    Avx2,
+    Simd128,
+}
+
+impl Backend {
+    /// Fastest backend this build can use on this CPU
+    pub fn detect() -> Self {
+        *Self::available().last().unwrap()
+    }
+
+    /// Every backend this build can use on this CPU, from the slowest to the fastest
+    pub fn available() -> Vec<Backend> {
+        // only gets pushed to on targets with SIMD backends
+        #[allow(unused_mut)]
+        let mut backends = vec![Backend::Scalar];
+        #[cfg(target_arch = "x86_64")]
+        {
+            backends.push(Backend::Sse2);
+            if is_x86_feature_detected!("avx2") {
+                backends.push(Backend::Avx2);
+            }
+        }
+        #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+        backends.push(Backend::Simd128);
+        backends
+    }
+}
+
+/// Bit `i` of the result is set if `seeds[i]` matches every input, seeds past `MAX_BATCH` are
+/// ignored
+pub fn verify_seeds(seeds: &[i32], infos: &[(i32, i32, i32, i32)]) -> u32 {
+    verify_seeds_on(Backend::detect(), seeds, infos)
+}
+
+/// Same as `verify_seeds` with a specific backend, backends that aren't available fall back to
+/// the scalar one
+pub fn verify_seeds_on(backend: Backend, seeds: &[i32], infos: &[(i32, i32, i32, i32)]) -> u32 {
+    let backend = usable(backend);
+    let seeds = &seeds[..seeds.len().min(MAX_BATCH)];
+    let mut mask = (1 << seeds.len()) - 1;
+    for &info in infos {
+        let mut matched = [0];
+        if let Some(check) = Check::new(info) {
+            match_lanes(backend, seeds, &check, &mut matched);
+        }
+        mask &= matched[0] as u32;
+        if mask == 0 {
+            break;
+        }
+    }
+    mask
+}
+
+fn usable(backend: Backend) -> Backend {
+    if Backend::available().contains(&backend) {
+        backend
+    } else {
+        Backend::Scalar
+    }
+}
+
+/// Seeds get buffered until there are this many, so the lanes run over long stretches of seeds
+const PENDING: usize = 4096;
+
+/// Collects the seeds that match every input, seeds get pushed one by one and checked in batches
+pub struct BatchFilter {
+    backend: Backend,
+    /// The alternatives of every input that aren't impossible
+    checks: Vec<Vec<Check>>,
+    pending: Vec<i32>,
+    scratch: Vec<i32>,
+    found: Vec<i32>,
+}
+
+impl BatchFilter {
+    pub fn new(infos: &[(i32, i32, i32, i32)]) -> Self {
+        Self::with_backend(Backend::detect(), infos)
+    }
+
+    /// Backends that aren't available fall back to the scalar one
+    pub fn with_backend(backend: Backend, infos: &[(i32, i32, i32, i32)]) -> Self {
+        let alternatives: Vec<Vec<_>> = infos.iter().map(|&info| vec![info]).collect();
+        Self::build(backend, &alternatives)
+    }
+
+    /// Each input is a list of alternatives, a seed has to match at least one of them
+    pub fn with_alternatives(infos: &[Vec<(i32, i32, i32, i32)>]) -> Self {
+        Self::build(Backend::detect(), infos)
+    }
+
+    fn build(backend: Backend, infos: &[Vec<(i32, i32, i32, i32)>]) -> Self {
+        BatchFilter {
+            backend: usable(backend),
+            checks: infos
+                .iter()
+                .map(|alternatives| alternatives.iter().filter_map(|&x| Check::new(x)).collect())
+                .collect(),
+            pending: Vec::with_capacity(PENDING),
+            scratch: Vec::new(),
+            found: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, seed: i32) {
+        self.pending.push(seed);
+        if self.pending.len() == PENDING {
+            self.flush();
+        }
+    }
+
+    /// Every matching seed, not always in the order they were pushed
+    pub fn finish(mut self) -> Vec<i32> {
+        self.flush();
+        self.found
+    }
+
+    fn flush(&mut self) {
+        // every input only gets the seeds that matched the ones before it
+        for alternatives in self.checks.iter() {
+            self.scratch.clear();
+            for check in alternatives {
+                filter_lanes(self.backend, &self.pending, check, &mut self.scratch);
+            }
+            if alternatives.len() > 1 {
+                // a seed can match more than one alternative
+                self.scratch.sort_unstable();
+                self.scratch.dedup();
+            }
+            std::mem::swap(&mut self.pending, &mut self.scratch);
+        }
+        self.found.append(&mut self.pending);
+    }
+}
+
+fn filter_lanes(backend: Backend, seeds: &[i32], check: &Check, out: &mut Vec<i32>) {
+    let mut matched = vec![0; seeds.len().div_ceil(64)];
+    match_lanes(backend, seeds, check, &mut matched);
+    for (word, mut bits) in matched.into_iter().enumerate() {
+        while bits != 0 {
+            out.push(seeds[word * 64 + bits.trailing_zeros() as usize]);
+            bits &= bits - 1;
+        }
+    }
+}
+
+/// Sets bit `i` of `matched` if `seeds[i]` matches `check`, it needs a bit for every seed and
+/// has to start cleared
+fn match_lanes(backend: Backend, seeds: &[i32], check: &Check, matched: &mut [u64]) {
+    // SAFETY: only backends from `Backend::available` make it here
+    unsafe {
+        match backend {
+            #[cfg(target_arch = "x86_64")]
+            Backend::Sse2 => matching::<x86::Sse2>(seeds, check, matched),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => x86::matching_avx2(seeds, check, matched),
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Backend::Simd128 => matching::<wasm::Simd128>(seeds, check, matched),
+            _ => matching::<u64>(seeds, check, matched),
+        }
+    }
+}
+
+/// Operations on a group of 64 bit lanes, callers have to make sure the CPU supports them
+trait Lanes: Copy {
+    const WIDTH: usize;
+    /// The states right after `set_seed`, `seeds` has exactly `WIDTH` seeds
+    unsafe fn seeds(seeds: &[i32]) -> Self;
+    unsafe fn splat(x: u64) -> Self;
+    unsafe fn add(self, other: Self) -> Self;
+    unsafe fn sub(self, other: Self) -> Self;
+    unsafe fn and(self, other: Self) -> Self;
+    unsafe fn shl(self, n: i32) -> Self;
+    unsafe fn shr(self, n: i32) -> Self;
+    /// Full 64 bit product of the low 32 bits of both lanes
+    unsafe fn mul32(self, other: Self) -> Self;
+    /// One bit per lane, set if the low 32 bits are between `lo` and `hi`, both signed
+    unsafe fn in_range(self, lo: u32, hi: u32) -> u32;
+
+    /// Next LCG state, the product gets split in 32 bit halves for lanes without a 64 bit multiply
+    #[inline(always)]
+    unsafe fn step(self) -> Self {
+        let (low, high) = (
+            Self::splat(MULT as u64 & 0xFFFF_FFFF),
+            Self::splat(MULT as u64 >> 32),
+        );
+        let cross = self.shr(32).mul32(low).add(self.mul32(high));
+        self.mul32(low)
+            .add(cross.shl(32))
+            .add(Self::splat(ADDEND as u64))
+            .and(Self::splat(MASK as u64))
+    }
+}
+
+/// The first slot of `SimpleRandom::verify_seed` on `L::WIDTH` seeds at a time, most seeds
+/// already fail there. The few that don't get checked by the scalar code, lanes would mostly be
+/// empty after that anyways
+#[inline(always)]
+unsafe fn matching<L: Lanes>(seeds: &[i32], check: &Check, matched: &mut [u64]) {
+    // the lanes go first without any branches, then the survivors get checked one by one
+    let groups = seeds.chunks_exact(L::WIDTH);
+    let rest = groups.remainder().len();
+    for (i, group) in groups.enumerate() {
+        let start = i * L::WIDTH;
+        matched[start / 64] |= (first_slot::<L>(group, check) as u64) << (start % 64);
+    }
+    for i in seeds.len() - rest..seeds.len() {
+        matched[i / 64] |= 1 << (i % 64);
+    }
+    let mut rng: SimpleRandom = Default::default();
+    for (word, bits) in matched.iter_mut().enumerate() {
+        let mut survivors = *bits;
+        while survivors != 0 {
+            let bit = survivors.trailing_zeros();
+            survivors &= survivors - 1;
+            if !rng.verify_seed(seeds[word * 64 + bit as usize], check.info) {
+                *bits &= !(1 << bit);
+            }
+        }
+    }
+}
+
+/// One bit per seed, set if the seed can show the first slot's level or if it goes through
+/// java's rejection loop, which the lanes can't follow
+#[inline(always)]
+unsafe fn first_slot<L: Lanes>(seeds: &[i32], check: &Check) -> u32 {
+    let state = L::seeds(seeds).step();
+    let first = state.shr(45);
+    let r = state.step().shr(17);
+    let (lo, hi) = check.sums[0];
+    let lanes = (1 << L::WIDTH) - 1;
+    if check.power_of_two {
+        let second = r.mul32(L::splat(check.bound)).shr(31);
+        first.add(second).in_range(lo, hi) & lanes
+    } else {
+        let quotient = r.mul32(L::splat(check.magic)).shr(check.shift);
+        let second = r.sub(quotient.mul32(L::splat(check.bound)));
+        let max = i32::MAX as u32 - check.shelves as u32;
+        let rejected = !r.sub(second).in_range(0, max);
+        (first.add(second).in_range(lo, hi) | rejected) & lanes
+    }
+}
+
+impl Lanes for u64 {
+    const WIDTH: usize = 1;
+
+    #[inline(always)]
+    unsafe fn seeds(seeds: &[i32]) -> Self {
+        (seeds[0] as i64 as u64 ^ MULT as u64) & MASK as u64
+    }
+
+    #[inline(always)]
+    unsafe fn splat(x: u64) -> Self {
+        x
+    }
+
+    #[inline(always)]
+    unsafe fn add(self, other: Self) -> Self {
+        self.wrapping_add(other)
+    }
+
+    #[inline(always)]
+    unsafe fn sub(self, other: Self) -> Self {
+        self.wrapping_sub(other)
+    }
+
+    #[inline(always)]
+    unsafe fn and(self, other: Self) -> Self {
+        self & other
+    }
+
+    #[inline(always)]
+    unsafe fn shl(self, n: i32) -> Self {
+        self << n
+    }
+
+    #[inline(always)]
+    unsafe fn shr(self, n: i32) -> Self {
+        self >> n
+    }
+
+    #[inline(always)]
+    unsafe fn mul32(self, other: Self) -> Self {
+        (self & 0xFFFF_FFFF) * (other & 0xFFFF_FFFF)
+    }
+
+    #[inline(always)]
+    unsafe fn in_range(self, lo: u32, hi: u32) -> u32 {
+        let x = self as u32 as i32;
+        (lo as i32 <= x && x <= hi as i32) as u32
+    }
+
+    #[inline(always)]
+    unsafe fn step(self) -> Self {
+        self.wrapping_mul(MULT as u64).wrapping_add(ADDEND as u64) & MASK as u64
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{matching, Check, Lanes, MASK, MULT};
+    use std::arch::x86_64::*;
+
+    #[derive(Copy, Clone)]
+    pub struct Sse2(__m128i);
+
+    impl Lanes for Sse2 {
+        const WIDTH: usize = 2;
+
+        #[inline(always)]
+        unsafe fn seeds(seeds: &[i32]) -> Self {
+            let seeds = _mm_set_epi64x(seeds[1] as i64, seeds[0] as i64);
+            let mult = _mm_set1_epi64x(MULT);
+            Sse2(_mm_xor_si128(seeds, mult)).and(Sse2::splat(MASK as u64))
+        }
+
+        #[inline(always)]
+        unsafe fn splat(x: u64) -> Self {
+            Sse2(_mm_set1_epi64x(x as i64))
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            Sse2(_mm_add_epi64(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            Sse2(_mm_sub_epi64(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn and(self, other: Self) -> Self {
+            Sse2(_mm_and_si128(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn shl(self, n: i32) -> Self {
+            Sse2(_mm_sll_epi64(self.0, _mm_cvtsi32_si128(n)))
+        }
+
+        #[inline(always)]
+        unsafe fn shr(self, n: i32) -> Self {
+            Sse2(_mm_srl_epi64(self.0, _mm_cvtsi32_si128(n)))
+        }
+
+        #[inline(always)]
+        unsafe fn mul32(self, other: Self) -> Self {
+            Sse2(_mm_mul_epu32(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn in_range(self, lo: u32, hi: u32) -> u32 {
+            let below = _mm_cmplt_epi32(self.0, _mm_set1_epi32(lo as i32));
+            let above = _mm_cmpgt_epi32(self.0, _mm_set1_epi32(hi as i32));
+            // only the low half of every lane matters, that's bytes 0 and 8
+            let outside = _mm_movemask_epi8(_mm_or_si128(below, above)) as u32;
+            !(outside & 1 | outside >> 7 & 2) & 0b11
+        }
+    }
+
+    #[derive(Copy, Clone)]
+    pub struct Avx2(__m256i);
+
+    impl Lanes for Avx2 {
+        const WIDTH: usize = 4;
+
+        #[inline(always)]
+        unsafe fn seeds(seeds: &[i32]) -> Self {
+            let seeds = _mm256_cvtepi32_epi64(_mm_loadu_si128(seeds.as_ptr() as *const __m128i));
+            let mult = _mm256_set1_epi64x(MULT);
+            Avx2(_mm256_xor_si256(seeds, mult)).and(Avx2::splat(MASK as u64))
+        }
+
+        #[inline(always)]
+        unsafe fn splat(x: u64) -> Self {
+            Avx2(_mm256_set1_epi64x(x as i64))
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            Avx2(_mm256_add_epi64(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            Avx2(_mm256_sub_epi64(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn and(self, other: Self) -> Self {
+            Avx2(_mm256_and_si256(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn shl(self, n: i32) -> Self {
+            Avx2(_mm256_sll_epi64(self.0, _mm_cvtsi32_si128(n)))
+        }
+
+        #[inline(always)]
+        unsafe fn shr(self, n: i32) -> Self {
+            Avx2(_mm256_srl_epi64(self.0, _mm_cvtsi32_si128(n)))
+        }
+
+        #[inline(always)]
+        unsafe fn mul32(self, other: Self) -> Self {
+            Avx2(_mm256_mul_epu32(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn in_range(self, lo: u32, hi: u32) -> u32 {
+            let below = _mm256_cmpgt_epi32(_mm256_set1_epi32(lo as i32), self.0);
+            let above = _mm256_cmpgt_epi32(self.0, _mm256_set1_epi32(hi as i32));
+            let outside = _mm256_movemask_epi8(_mm256_or_si256(below, above)) as u32;
+            let lanes = outside & 1 | outside >> 7 & 2 | outside >> 14 & 4 | outside >> 21 & 8;
+            !lanes & 0b1111
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn matching_avx2(seeds: &[i32], check: &Check, matched: &mut [u64]) {
+        matching::<Avx2>(seeds, check, matched)
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm {
+    use super::Lanes;
+    use std::arch::wasm32::*;
+
+    #[derive(Copy, Clone)]
+    pub struct Simd128(v128);
+
+    impl Lanes for Simd128 {
+        const WIDTH: usize = 2;
+
+        #[inline(always)]
+        unsafe fn seeds(seeds: &[i32]) -> Self {
+            let seeds = i64x2(seeds[0] as i64, seeds[1] as i64);
+            let state = v128_xor(seeds, u64x2_splat(super::MULT as u64));
+            Simd128(v128_and(state, u64x2_splat(super::MASK as u64)))
+        }
+
+        #[inline(always)]
+        unsafe fn splat(x: u64) -> Self {
+            Simd128(u64x2_splat(x))
+        }
+
+        #[inline(always)]
+        unsafe fn add(self, other: Self) -> Self {
+            Simd128(i64x2_add(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn sub(self, other: Self) -> Self {
+            Simd128(i64x2_sub(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn and(self, other: Self) -> Self {
+            Simd128(v128_and(self.0, other.0))
+        }
+
+        #[inline(always)]
+        unsafe fn shl(self, n: i32) -> Self {
+            Simd128(i64x2_shl(self.0, n as u32))
+        }
+
+        #[inline(always)]
+        unsafe fn shr(self, n: i32) -> Self {
+            Simd128(u64x2_shr(self.0, n as u32))
+        }
+
+        #[inline(always)]
+        unsafe fn mul32(self, other: Self) -> Self {
+            let low = u64x2_splat(0xFFFF_FFFF);
+            Simd128(i64x2_mul(v128_and(self.0, low), v128_and(other.0, low)))
+        }
+
+        #[inline(always)]
+        unsafe fn in_range(self, lo: u32, hi: u32) -> u32 {
+            // every value that gets compared fits in 32 bits, so a 64 bit compare is the same
+            let x = i64x2_shr(i64x2_shl(self.0, 32), 32);
+            let inside = v128_and(
+                i64x2_ge(x, i64x2_splat(lo as i32 as i64)),
+                i64x2_le(x, i64x2_splat(hi as i32 as i64)),
+            );
+            i64x2_bitmask(inside) as u32
+        }
+
+        #[inline(always)]
+        unsafe fn step(self) -> Self {
+            let state = i64x2_mul(self.0, u64x2_splat(super::MULT as u64));
+            let state = i64x2_add(state, u64x2_splat(super::ADDEND as u64));
+            Simd128(v128_and(state, u64x2_splat(super::MASK as u64)))
+        }
+    }
+}
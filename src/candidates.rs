@@ -0,0 +1,367 @@
+use crate::search::SEED_SPACE;
+use crate::Error;
+use std::ops::Range;
+
+const FORMAT_VERSION: u8 = 1;
+
+/// A sorted vector takes 32 bits per candidate while the bitmap takes 1 bit per seed in range, so
+/// the bitmap wins once more than 1 in 32 seeds are candidates. Going back to the vector waits
+/// until it's half of that so a set right on the edge doesn't keep switching
+const DENSE_RATIO: i64 = 32;
+const SPARSE_RATIO: i64 = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Storage {
+    /// Sorted and without duplicates
+    Sparse(Vec<i32>),
+    /// One bit per seed in the range, starting from `range.start`
+    Dense { bits: Vec<u64>, len: usize },
+}
+
+/// Candidate XP seeds, stored either as a sorted vector or as a bitmap over the seeds of the
+/// range, whichever is smaller. It switches by itself as candidates get added or filtered out
+#[derive(Clone, Debug, PartialEq)]
+pub struct CandidateSet {
+    range: Range<i64>,
+    storage: Storage,
+}
+
+impl CandidateSet {
+    /// An empty set for seeds inside of `range`, it grows if seeds outside of it get added
+    pub fn new(range: Range<i64>) -> Self {
+        CandidateSet {
+            range,
+            storage: Storage::Sparse(Vec::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Sparse(v) => v.len(),
+            Storage::Dense { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_dense(&self) -> bool {
+        matches!(self.storage, Storage::Dense { .. })
+    }
+
+    /// Seeds the set is sized for
+    pub fn range(&self) -> Range<i64> {
+        self.range.clone()
+    }
+
+    pub fn clear(&mut self) {
+        self.storage = Storage::Sparse(Vec::new());
+    }
+
+    pub fn contains(&self, seed: i32) -> bool {
+        match &self.storage {
+            Storage::Sparse(v) => v.binary_search(&seed).is_ok(),
+            Storage::Dense { bits, .. } => {
+                if !self.range.contains(&(seed as i64)) {
+                    return false;
+                }
+                let i = (seed as i64 - self.range.start) as usize;
+                bits[i / 64] & (1 << (i % 64)) != 0
+            }
+        }
+    }
+
+    /// Every candidate, from the lowest to the highest
+    pub fn iter(&self) -> Iter<'_> {
+        match &self.storage {
+            Storage::Sparse(v) => Iter::Sparse(v.iter()),
+            Storage::Dense { bits, .. } => Iter::Dense {
+                start: self.range.start,
+                bits,
+                word: 0,
+                current: bits.first().copied().unwrap_or(0),
+            },
+        }
+    }
+
+    /// Adds a seed, it's fast as long as seeds are pushed from the lowest to the highest
+    pub fn push(&mut self, seed: i32) {
+        if !self.range.contains(&(seed as i64)) {
+            self.extend(std::iter::once(seed));
+            return;
+        }
+        match &mut self.storage {
+            Storage::Sparse(v) => match v.last() {
+                Some(&last) if last >= seed => {
+                    if let Err(i) = v.binary_search(&seed) {
+                        v.insert(i, seed);
+                    }
+                }
+                _ => v.push(seed),
+            },
+            Storage::Dense { bits, len } => {
+                let i = (seed as i64 - self.range.start) as usize;
+                if bits[i / 64] & (1 << (i % 64)) == 0 {
+                    bits[i / 64] |= 1 << (i % 64);
+                    *len += 1;
+                }
+            }
+        }
+    }
+
+    /// Adds every seed, growing the range if some of them are outside of it
+    pub fn extend(&mut self, seeds: impl IntoIterator<Item = i32>) {
+        let mut seeds: Vec<i32> = seeds.into_iter().collect();
+        seeds.sort_unstable();
+        seeds.dedup();
+        let (first, last) = match (seeds.first(), seeds.last()) {
+            (Some(&first), Some(&last)) => (first as i64, last as i64),
+            _ => return,
+        };
+        if first < self.range.start || last >= self.range.end {
+            // the bitmap can't grow in place, go through the vector
+            self.make_sparse();
+            self.range = first.min(self.range.start)..(last + 1).max(self.range.end);
+        }
+        match &mut self.storage {
+            Storage::Sparse(v) if v.is_empty() => *v = seeds,
+            // searches go from the lowest seed to the highest so new seeds usually go at the end
+            Storage::Sparse(v) if v.last() < seeds.first() => v.append(&mut seeds),
+            Storage::Sparse(v) => {
+                v.append(&mut seeds);
+                // stable sort because it's fast at merging already sorted runs
+                v.sort();
+                v.dedup();
+            }
+            Storage::Dense { .. } => {
+                for seed in seeds {
+                    self.push(seed);
+                }
+            }
+        }
+        self.rebalance();
+    }
+
+    /// Keeps only the candidates for which `f` returns true
+    pub fn retain(&mut self, mut f: impl FnMut(i32) -> bool) {
+        match &mut self.storage {
+            Storage::Sparse(v) => v.retain(|&x| f(x)),
+            Storage::Dense { bits, len } => {
+                let start = self.range.start;
+                for (i, word) in bits.iter_mut().enumerate() {
+                    let mut rest = *word;
+                    while rest != 0 {
+                        let bit = rest.trailing_zeros() as i64;
+                        rest &= rest - 1;
+                        if !f((start + i as i64 * 64 + bit) as i32) {
+                            *word &= !(1 << bit);
+                            *len -= 1;
+                        }
+                    }
+                }
+            }
+        }
+        self.rebalance();
+    }
+
+    /// Switches to whichever storage is smaller for the amount of candidates there are now
+    pub fn rebalance(&mut self) {
+        let span = self.range.end - self.range.start;
+        let len = self.len() as i64;
+        match self.storage {
+            Storage::Sparse(_) if len * DENSE_RATIO > span => self.make_dense(),
+            Storage::Dense { .. } if len * SPARSE_RATIO < span => self.make_sparse(),
+            _ => {}
+        }
+    }
+
+    fn make_sparse(&mut self) {
+        if let Storage::Dense { .. } = self.storage {
+            self.storage = Storage::Sparse(self.iter().collect());
+        }
+    }
+
+    fn make_dense(&mut self) {
+        if let Storage::Sparse(v) = &self.storage {
+            let span = (self.range.end - self.range.start) as usize;
+            let mut bits = vec![0u64; span.div_ceil(64)];
+            for &seed in v {
+                let i = (seed as i64 - self.range.start) as usize;
+                bits[i / 64] |= 1 << (i % 64);
+            }
+            self.storage = Storage::Dense { bits, len: v.len() };
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        encode_sorted(self.len(), self.iter())
+    }
+
+    pub fn encode_json(&self) -> String {
+        encode_json_sorted(self.iter())
+    }
+}
+
+impl Default for CandidateSet {
+    fn default() -> Self {
+        Self::new(SEED_SPACE)
+    }
+}
+
+pub enum Iter<'a> {
+    Sparse(std::slice::Iter<'a, i32>),
+    Dense {
+        start: i64,
+        bits: &'a [u64],
+        word: usize,
+        current: u64,
+    },
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<i32> {
+        match self {
+            Iter::Sparse(iter) => iter.next().copied(),
+            Iter::Dense {
+                start,
+                bits,
+                word,
+                current,
+            } => {
+                while *current == 0 {
+                    *word += 1;
+                    *current = *bits.get(*word)?;
+                }
+                let bit = current.trailing_zeros() as i64;
+                *current &= *current - 1;
+                Some((*start + *word as i64 * 64 + bit) as i32)
+            }
+        }
+    }
+
+    /// Skips whole words of the bitmap at a time, paging through a dense set doesn't have to
+    /// walk every candidate before the page
+    fn nth(&mut self, mut n: usize) -> Option<i32> {
+        if let Iter::Sparse(iter) = self {
+            return iter.nth(n).copied();
+        }
+        if let Iter::Dense {
+            bits,
+            word,
+            current,
+            ..
+        } = self
+        {
+            while current.count_ones() as usize <= n {
+                n -= current.count_ones() as usize;
+                *word += 1;
+                *current = 0;
+                *current = *bits.get(*word)?;
+            }
+            for _ in 0..n {
+                *current &= *current - 1;
+            }
+        }
+        self.next()
+    }
+}
+
+pub(crate) fn write_varint(bytes: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        bytes.push(x as u8 | 0x80);
+        x >>= 7;
+    }
+    bytes.push(x as u8);
+}
+
+/// `None` if the bytes run out or it goes past 64 bits
+pub(crate) fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Option<u64> {
+    let mut x = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = bytes.next()?;
+        x |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(x);
+        }
+    }
+    None
+}
+
+/// Seeds get sorted and stored as the offset of the first one from `i32::MIN` followed by the
+/// differences between each of them, all as varints. Dense candidate sets end up with most
+/// seeds taking a single byte
+pub fn encode(seeds: &[i32]) -> Vec<u8> {
+    let mut sorted = seeds.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    encode_sorted(sorted.len(), sorted.into_iter())
+}
+
+fn encode_sorted(len: usize, seeds: impl Iterator<Item = i32>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(len + 8);
+    bytes.push(FORMAT_VERSION);
+    write_varint(&mut bytes, len as u64);
+    let mut last = i32::MIN as i64;
+    for seed in seeds {
+        write_varint(&mut bytes, (seed as i64 - last) as u64);
+        last = seed as i64;
+    }
+    bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Vec<i32>, Error> {
+    let mut iter = bytes.iter().copied();
+    if iter.next() != Some(FORMAT_VERSION) {
+        return Err(Error::InvalidCandidates);
+    }
+    let len = read_varint(&mut iter).ok_or(Error::InvalidCandidates)?;
+    // every seed takes at least one byte, don't trust the length before checking that
+    if len > bytes.len() as u64 {
+        return Err(Error::InvalidCandidates);
+    }
+    let mut seeds = Vec::with_capacity(len as usize);
+    let mut last = i32::MIN as i64;
+    for i in 0..len {
+        let delta = read_varint(&mut iter).ok_or(Error::InvalidCandidates)?;
+        if (i > 0 && delta == 0) || delta > u32::MAX as u64 {
+            return Err(Error::InvalidCandidates);
+        }
+        last += delta as i64;
+        if last > i32::MAX as i64 {
+            return Err(Error::InvalidCandidates);
+        }
+        seeds.push(last as i32);
+    }
+    if iter.next().is_some() {
+        return Err(Error::InvalidCandidates);
+    }
+    Ok(seeds)
+}
+
+/// A plain JSON array of numbers
+pub fn encode_json(seeds: &[i32]) -> String {
+    encode_json_sorted(seeds.iter().copied())
+}
+
+fn encode_json_sorted(seeds: impl Iterator<Item = i32>) -> String {
+    let numbers: Vec<String> = seeds.map(|x| x.to_string()).collect();
+    format!("[{}]", numbers.join(","))
+}
+
+pub fn decode_json(json: &str) -> Result<Vec<i32>, Error> {
+    let inner = json
+        .trim()
+        .strip_prefix('[')
+        .and_then(|x| x.strip_suffix(']'))
+        .ok_or(Error::InvalidCandidates)?;
+    if inner.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    inner
+        .split(',')
+        .map(|x| x.trim().parse().map_err(|_| Error::InvalidCandidates))
+        .collect()
+}
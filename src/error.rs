@@ -0,0 +1,112 @@
+use crate::manipulation::{Item, Version};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// No player seed can produce the given XP seeds
+    NoSeedFound,
+    /// Recovering the player seed needs at least one XP seed
+    NoXpSeeds,
+    /// There has to be a gap between each XP seed and the next one
+    WrongGapCount { seeds: usize, gaps: usize },
+    /// More than one seed is still possible, carries how many are left
+    AmbiguousSeed(usize),
+    /// Every candidate got filtered out, usually because of a wrong input
+    EmptyCandidateSet,
+    /// Searching needs at least one input
+    NoInputs,
+    /// The search was stopped through a `CancellationToken`
+    Cancelled,
+    /// The threads for a `Cracker` with its own thread pool couldn't be started
+    ThreadPool,
+    /// The bytes don't describe a valid `SearchCursor`
+    InvalidCursor,
+    /// The data doesn't describe a valid set of candidates
+    InvalidCandidates,
+    /// Merging needs at least one shard
+    NoShards,
+    /// Shards can't be merged when neither of them went through all of the observations of the
+    /// other
+    DifferentHistories,
+    /// The cracker hasn't made that many observations
+    NoObservation(usize),
+    /// Enchanting tables only have slots 0 to 2
+    InvalidSlot(i32),
+    /// Enchanting tables only take into account 0 to 15 bookshelves
+    InvalidShelves(i32),
+    /// Simulating needs at least one amount of bookshelves to try
+    NoShelves,
+    /// The lowest amount of bookshelves of a range is above the highest
+    InvalidShelfRange { min: i32, max: i32 },
+    /// No seed can show that level in that slot with that amount of bookshelves
+    ImpossibleLevel { shelves: i32, slot: i32, level: i32 },
+    /// Every slot of the input is unknown, it would match every seed
+    NoKnownLevels,
+    /// None of the possible amounts of bookshelves can show all three levels
+    InconsistentLevels,
+    /// The item doesn't exist yet in the given version
+    UnsupportedVersion(Item, Version),
+    /// There is nothing to look for because no enchantments were set for the item
+    NoWantedEnchantments(Item),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoSeedFound => write!(f, "no player seed matches the given XP seeds"),
+            Error::NoXpSeeds => write!(f, "at least one XP seed is needed"),
+            Error::WrongGapCount { seeds, gaps } => write!(
+                f,
+                "{} XP seeds need {} gaps but {} were given",
+                seeds,
+                seeds.saturating_sub(1),
+                gaps
+            ),
+            Error::AmbiguousSeed(n) => write!(f, "{} seeds are still possible, add more inputs", n),
+            Error::EmptyCandidateSet => {
+                write!(f, "no seeds are left, one of the inputs is probably wrong")
+            }
+            Error::NoInputs => write!(f, "at least one enchanting table input is needed"),
+            Error::Cancelled => write!(f, "the search was cancelled"),
+            Error::ThreadPool => write!(f, "the thread pool couldn't be started"),
+            Error::InvalidCursor => write!(f, "the search cursor is corrupted"),
+            Error::InvalidCandidates => write!(f, "the candidate data is corrupted"),
+            Error::NoShards => write!(f, "at least one shard is needed"),
+            Error::DifferentHistories => {
+                write!(f, "the shards went through different observations")
+            }
+            Error::NoObservation(index) => write!(f, "there is no observation {}", index),
+            Error::InvalidSlot(slot) => write!(f, "{} is not a valid enchanting table slot", slot),
+            Error::InvalidShelves(shelves) => {
+                write!(f, "{} bookshelves is out of range (0 to 15)", shelves)
+            }
+            Error::NoShelves => write!(f, "at least one amount of bookshelves is needed"),
+            Error::InvalidShelfRange { min, max } => {
+                write!(f, "{} to {} bookshelves is an empty range", min, max)
+            }
+            Error::ImpossibleLevel {
+                shelves,
+                slot,
+                level,
+            } => write!(
+                f,
+                "slot {} can't cost {} levels with {} bookshelves",
+                slot + 1,
+                level,
+                shelves
+            ),
+            Error::NoKnownLevels => write!(f, "at least one slot has to be seen"),
+            Error::InconsistentLevels => {
+                write!(f, "no amount of bookshelves can show those levels together")
+            }
+            Error::UnsupportedVersion(item, version) => {
+                write!(f, "{:?} doesn't exist in {:?}", item, version)
+            }
+            Error::NoWantedEnchantments(item) => {
+                write!(f, "no enchantments were set for {:?}", item)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,84 @@
+//! What a `Cracker` filtered its candidates with, kept around so that observations can be undone
+//! or edited without searching again.
+use crate::manipulation::*;
+use crate::random::JavaRandom;
+use crate::utils::SimpleRandom;
+use crate::{EnchantmentTableInfo, Error};
+
+/// Something seen at the enchanting table that tells candidates apart
+#[derive(Clone, Debug, PartialEq)]
+pub enum Observation {
+    /// The levels of the three slots, see `Cracker::add_input`
+    Input(EnchantmentTableInfo),
+    /// The enchantment shown when hovering over a zero-indexed slot, see `Cracker::add_clue`
+    Clue {
+        item: Item,
+        slot: i32,
+        info: EnchantmentTableInfo,
+        enchantment: Enchantment,
+        level: i32,
+        version: Version,
+    },
+}
+
+impl Observation {
+    /// Inputs are validated when they get built, clues only here
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Observation::Clue {
+            item,
+            slot,
+            version,
+            ..
+        } = *self
+        {
+            if !(0..=2).contains(&slot) {
+                return Err(Error::InvalidSlot(slot));
+            }
+            if version.before(item.get_introduced_version()) {
+                return Err(Error::UnsupportedVersion(item, version));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn matches(&self, rng: &mut SimpleRandom, seed: i32) -> bool {
+        match *self {
+            Observation::Input(info) => info.matches(rng, seed),
+            Observation::Clue {
+                item,
+                slot,
+                info,
+                enchantment,
+                level,
+                version,
+            } => {
+                let clue = Some(EnchantmentInstance::new(enchantment, level));
+                info.alternatives().any(|alternative| {
+                    rng.verify_seed(seed, alternative)
+                        && table_clue(rng, seed, alternative.0, item, slot, version) == clue
+                })
+            }
+        }
+    }
+}
+
+/// The clue of a zero-indexed slot for an XP seed, `None` if the slot is empty
+fn table_clue(
+    rand: &mut impl JavaRandom,
+    xp_seed: i32,
+    shelves: i32,
+    item: Item,
+    slot: i32,
+    version: Version,
+) -> Option<EnchantmentInstance> {
+    rand.set_seed(xp_seed as i64);
+    let mut level = 0;
+    for i in 0..=slot {
+        level = Enchantment::calc_enchantment_table_level(rand, i, shelves, item).ok()?;
+    }
+    // the table hides levels that are below the slot number
+    if level < slot + 1 {
+        return None;
+    }
+    Enchantment::get_enchantment_clue(rand, xp_seed, item, slot, level, version)
+}
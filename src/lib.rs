@@ -1,152 +1,816 @@
 extern crate strum;
 #[macro_use]
 extern crate strum_macros;
+pub mod batch;
+pub mod candidates;
+pub mod error;
+pub mod history;
 pub mod manipulation;
+pub mod player;
+pub mod progress;
+pub mod random;
+pub mod search;
 pub mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
+use crate::batch::BatchFilter;
+use crate::candidates::CandidateSet;
+pub use crate::error::Error;
+pub use crate::history::Observation;
 use crate::manipulation::*;
-use crate::utils::SimpleRandom;
+use crate::player::Gap;
+use crate::progress::*;
+use crate::random::JavaRandom;
+use crate::search::*;
+use crate::utils::{BitConstraints, Lcg48, SimpleRandom, UNKNOWN_LEVEL};
 use enum_map::EnumMap;
-use std::num::Wrapping;
-use strum::IntoEnumIterator;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-#[cfg(not(feature = "threads"))]
 use std::ops::Range;
 
-#[cfg(feature = "threads")]
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
-#[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+/// Seeds each thread walks at a time when searching in parallel
+#[cfg(feature = "parallel")]
+const SEARCH_PIECE: usize = 1 << 16;
 
-const PREALLOC_SIZE: usize = 80e6 as usize;
+/// Most candidates looked at when estimating how much the next input narrows them down
+const ESTIMATE_SAMPLE: usize = 1 << 16;
 
-#[cfg(feature = "threads")]
-pub use wasm_bindgen_rayon::init_thread_pool;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+#[global_allocator]
+static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
-#[wasm_bindgen]
-#[derive(Copy, Clone)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct EnchantmentTableInfo {
-    shelves: i32,
+    /// Bit `n` is set if the table could have `n` bookshelves
+    shelves: u16,
     slot1: i32,
     slot2: i32,
     slot3: i32,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl EnchantmentTableInfo {
-    #[wasm_bindgen(constructor)]
-    pub fn new(shelves: i32, slot1: i32, slot2: i32, slot3: i32) -> Self {
-        EnchantmentTableInfo {
-            shelves,
+    /// Checks that the levels can actually show up with that amount of bookshelves, each slot
+    /// rolls its own level so this also rules out impossible orderings between slots. A level of
+    /// 0 is a slot that wasn't seen, but at least one of them has to be seen
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(shelves: i32, slot1: i32, slot2: i32, slot3: i32) -> Result<Self, Error> {
+        if !(0..=15).contains(&shelves) {
+            return Err(Error::InvalidShelves(shelves));
+        }
+        if [slot1, slot2, slot3] == [UNKNOWN_LEVEL; 3] {
+            return Err(Error::NoKnownLevels);
+        }
+        for (slot, level) in [slot1, slot2, slot3].into_iter().enumerate() {
+            let slot = slot as i32;
+            if !utils::can_show(shelves, slot, level) {
+                return Err(Error::ImpossibleLevel {
+                    shelves,
+                    slot,
+                    level,
+                });
+            }
+        }
+        Ok(EnchantmentTableInfo {
+            shelves: 1 << shelves,
             slot1,
             slot2,
             slot3,
+        })
+    }
+
+    /// For when only some of the slots could be seen, `None` slots are left out of the checks
+    #[cfg_attr(feature = "wasm", wasm_bindgen)]
+    pub fn partial(
+        shelves: i32,
+        slot1: Option<i32>,
+        slot2: Option<i32>,
+        slot3: Option<i32>,
+    ) -> Result<Self, Error> {
+        let [slot1, slot2, slot3] = [slot1, slot2, slot3].map(|x| x.unwrap_or(UNKNOWN_LEVEL));
+        Self::new(shelves, slot1, slot2, slot3)
+    }
+
+    /// For when the amount of bookshelves is only known to be between `min` and `max`, the
+    /// amounts that can't show those levels get left out. Slot 3 is never below twice the
+    /// bookshelves so it usually leaves only a few of them
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = withShelfRange))]
+    pub fn with_shelf_range(
+        min: i32,
+        max: i32,
+        slot1: i32,
+        slot2: i32,
+        slot3: i32,
+    ) -> Result<Self, Error> {
+        for shelves in [min, max] {
+            if !(0..=15).contains(&shelves) {
+                return Err(Error::InvalidShelves(shelves));
+            }
+        }
+        if min > max {
+            return Err(Error::InvalidShelfRange { min, max });
         }
+        let mask = (min..=max).fold(0, |mask, shelves| mask | 1 << shelves);
+        Self::with_shelf_mask(mask, slot1, slot2, slot3)
+    }
+
+    /// For when the amount of bookshelves isn't known at all
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = unknownShelves))]
+    pub fn unknown_shelves(slot1: i32, slot2: i32, slot3: i32) -> Result<Self, Error> {
+        Self::with_shelf_range(0, 15, slot1, slot2, slot3)
+    }
+
+    /// The amount of bookshelves, only if a single one is possible
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    pub fn shelves(&self) -> Option<i32> {
+        match self.shelves.count_ones() {
+            1 => Some(self.shelves.trailing_zeros() as i32),
+            _ => None,
+        }
+    }
+
+    #[cfg_attr(feature = "wasm", wasm_bindgen(js_name = possibleShelves))]
+    pub fn possible_shelves(&self) -> Vec<i32> {
+        self.shelf_counts().collect()
     }
 }
 
-impl From<EnchantmentTableInfo> for (i32, i32, i32, i32) {
-    fn from(x: EnchantmentTableInfo) -> Self {
-        (x.shelves, x.slot1, x.slot2, x.slot3)
+impl EnchantmentTableInfo {
+    /// Keeps the amounts of bookshelves of `mask` that can show the levels
+    pub(crate) fn with_shelf_mask(
+        mask: u16,
+        slot1: i32,
+        slot2: i32,
+        slot3: i32,
+    ) -> Result<Self, Error> {
+        if [slot1, slot2, slot3] == [UNKNOWN_LEVEL; 3] {
+            return Err(Error::NoKnownLevels);
+        }
+        let shelves = (0..16)
+            .filter(|&shelves| mask & 1 << shelves != 0)
+            .filter(|&shelves| {
+                [slot1, slot2, slot3]
+                    .into_iter()
+                    .enumerate()
+                    .all(|(slot, level)| utils::can_show(shelves, slot as i32, level))
+            })
+            .fold(0, |mask, shelves| mask | 1 << shelves);
+        if shelves == 0 {
+            return Err(Error::InconsistentLevels);
+        }
+        Ok(EnchantmentTableInfo {
+            shelves,
+            slot1,
+            slot2,
+            slot3,
+        })
+    }
+
+    pub(crate) fn shelf_mask(&self) -> u16 {
+        self.shelves
+    }
+
+    /// Same as `possible_shelves`, straight from the bits without a `Vec`
+    fn shelf_counts(&self) -> impl Iterator<Item = i32> {
+        let mut rest = self.shelves;
+        std::iter::from_fn(move || {
+            if rest == 0 {
+                return None;
+            }
+            let shelves = rest.trailing_zeros() as i32;
+            rest &= rest - 1;
+            Some(shelves)
+        })
+    }
+
+    pub fn levels(&self) -> (i32, i32, i32) {
+        (self.slot1, self.slot2, self.slot3)
+    }
+
+    /// `(shelves, slot1, slot2, slot3)` for every amount of bookshelves the table could have, a
+    /// seed matches if it matches any of them
+    pub fn alternatives(&self) -> impl Iterator<Item = (i32, i32, i32, i32)> + '_ {
+        self.shelf_counts()
+            .map(|shelves| (shelves, self.slot1, self.slot2, self.slot3))
+    }
+
+    pub fn matches(&self, rng: &mut SimpleRandom, seed: i32) -> bool {
+        self.alternatives().any(|info| rng.verify_seed(seed, info))
     }
 }
 
-#[wasm_bindgen]
 pub struct Cracker {
-    possible_seeds: Vec<i32>,
-    #[cfg(not(feature = "threads"))]
-    start_size: Range<i32>,
-    #[cfg(not(feature = "threads"))]
-    thread_id: usize,
-    #[cfg(not(feature = "threads"))]
-    threads: usize,
+    possible_seeds: CandidateSet,
+    #[cfg(not(feature = "parallel"))]
+    start_size: Range<i64>,
+    /// Searches go through rayon's global pool when there isn't one
+    #[cfg(feature = "parallel")]
+    pool: Option<rayon::ThreadPool>,
     rng: SimpleRandom,
+    /// How many observations a seed can miss before it gets dropped
+    tolerance: usize,
+    /// Observations made with `add_input` and `add_clue` since the last reset
+    history: Vec<Observation>,
+    /// Observations taken back with `undo`, the last one is the next to redo
+    undone: Vec<Observation>,
+    /// The candidates from before the first observation, they get filtered again when the
+    /// history changes
+    snapshot: Option<CandidateSet>,
+    /// The observations each candidate missed, only for the ones that missed some
+    mismatches: BTreeMap<i32, Vec<usize>>,
 }
 
-#[wasm_bindgen]
 impl Cracker {
-    #[cfg(not(feature = "threads"))]
-    #[wasm_bindgen(constructor)]
+    #[cfg(not(feature = "parallel"))]
     pub fn new(thread_id: usize, threads: usize) -> Self {
-        let size = u32::MAX / threads as u32;
-        let start = (i32::MIN as i64 + (size * thread_id as u32) as i64) as i32;
+        let size = SEED_SPACE.end - SEED_SPACE.start;
+        let (id, threads) = (thread_id as i64, threads as i64);
+        let start_size =
+            SEED_SPACE.start + size * id / threads..SEED_SPACE.start + size * (id + 1) / threads;
         Cracker {
-            possible_seeds: Vec::with_capacity((PREALLOC_SIZE) / threads),
-            start_size: start
-                ..(start as i64 + size as i64) as i32
-                    + if thread_id == threads - 1 {
-                        thread_id as i32
-                    } else {
-                        0
-                    },
+            possible_seeds: CandidateSet::new(start_size.clone()),
+            start_size,
             rng: Default::default(),
-            thread_id,
-            threads,
+            tolerance: 0,
+            history: Vec::new(),
+            undone: Vec::new(),
+            snapshot: None,
+            mismatches: BTreeMap::new(),
         }
     }
 
-    #[cfg(feature = "threads")]
-    #[wasm_bindgen(constructor)]
+    #[cfg(feature = "parallel")]
     pub fn new() -> Self {
         Cracker {
-            possible_seeds: Vec::with_capacity(PREALLOC_SIZE),
+            possible_seeds: Default::default(),
+            pool: None,
             rng: Default::default(),
+            tolerance: 0,
+            history: Vec::new(),
+            undone: Vec::new(),
+            snapshot: None,
+            mismatches: BTreeMap::new(),
+        }
+    }
+
+    /// A cracker that searches with its own pool of `threads` threads instead of the global one
+    #[cfg(feature = "parallel")]
+    pub fn with_threads(threads: usize) -> Result<Self, Error> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|_| Error::ThreadPool)?;
+        Ok(Cracker {
+            pool: Some(pool),
+            ..Self::new()
+        })
+    }
+
+    /// How many threads the searches run on
+    #[cfg(feature = "parallel")]
+    pub fn threads(&self) -> usize {
+        match &self.pool {
+            Some(pool) => pool.current_num_threads(),
+            None => rayon::current_num_threads(),
         }
     }
 
-    #[wasm_bindgen]
     pub fn reset(&mut self) {
         self.possible_seeds.clear();
+        self.history.clear();
+        self.undone.clear();
+        self.snapshot = None;
+        self.mismatches.clear();
+    }
+
+    /// Lets candidates miss up to `tolerance` of the observations made with `add_input` and
+    /// `add_clue` so a single mistyped one doesn't drop the right seed, lowering it drops the
+    /// candidates that already missed too many, `undo` or `replay` bring them back. The searches
+    /// are always exact
+    pub fn set_tolerance(&mut self, tolerance: usize) {
+        self.tolerance = tolerance;
+        let mismatches = &mut self.mismatches;
+        self.possible_seeds.retain(|x| match mismatches.get(&x) {
+            Some(missed) if missed.len() > tolerance => {
+                mismatches.remove(&x);
+                false
+            }
+            _ => true,
+        });
+    }
+
+    pub fn tolerance(&self) -> usize {
+        self.tolerance
+    }
+
+    /// Indices into `observations` of the ones a candidate doesn't match
+    pub fn mismatches(&self, seed: i32) -> Vec<usize> {
+        self.mismatches.get(&seed).cloned().unwrap_or_default()
+    }
+
+    /// Candidates that miss exactly `count` observations, with 0 that's every candidate that
+    /// matches all of them
+    pub fn seeds_with_mismatches(&self, count: usize) -> Vec<i32> {
+        match count {
+            0 => self
+                .possible_seeds
+                .iter()
+                .filter(|x| !self.mismatches.contains_key(x))
+                .collect(),
+            _ => self
+                .mismatches
+                .iter()
+                .filter(|(_, missed)| missed.len() == count)
+                .map(|(&x, _)| x)
+                .collect(),
+        }
     }
 
-    #[wasm_bindgen(getter = possibleSeeds)]
     pub fn possible_seeds(&self) -> usize {
         self.possible_seeds.len()
     }
 
-    #[wasm_bindgen(getter)]
-    pub fn seed(&self) -> i32 {
-        self.possible_seeds[0]
+    /// How many candidates are expected to be left after one more input with that many
+    /// bookshelves, if the right seed could be any of the candidates. With lots of candidates it's
+    /// estimated from `ESTIMATE_SAMPLE` of them
+    pub fn expected_remaining(&self, shelves: i32) -> Result<f64, Error> {
+        if !(0..=15).contains(&shelves) {
+            return Err(Error::InvalidShelves(shelves));
+        }
+        Ok(self.estimate(&self.sample()?, shelves))
+    }
+
+    /// The amount of bookshelves to use for the next input so it leaves the fewest candidates
+    pub fn best_shelves(&self) -> Result<i32, Error> {
+        let sample = self.sample()?;
+        let expected: Vec<f64> = (0..=15).map(|x| self.estimate(&sample, x)).collect();
+        Ok((0..=15)
+            .min_by(|&a, &b| expected[a as usize].total_cmp(&expected[b as usize]))
+            .unwrap())
     }
 
-    #[cfg(feature = "threads")]
-    #[wasm_bindgen(js_name = firstInput)]
-    pub fn first_input(&mut self, info: EnchantmentTableInfo, info2: EnchantmentTableInfo) {
-        self.possible_seeds
-            .par_extend((i32::MIN..=i32::MAX).into_par_iter().filter(|&x| {
-                let mut rng: SimpleRandom = Default::default();
-                rng.verify_seed(x, info.into()) && rng.verify_seed(x, info2.into())
-            }))
+    fn sample(&self) -> Result<Vec<i32>, Error> {
+        if self.possible_seeds.is_empty() {
+            return Err(Error::EmptyCandidateSet);
+        }
+        let step = cmp::max(self.possible_seeds.len() / ESTIMATE_SAMPLE, 1);
+        Ok(self.possible_seeds.iter().step_by(step).collect())
+    }
+
+    /// A candidate stays if it shows the same levels as the right seed, so that's the sum of
+    /// the squared sizes of each group of levels over the amount of candidates
+    fn estimate(&self, sample: &[i32], shelves: i32) -> f64 {
+        let mut rng: SimpleRandom = Default::default();
+        let mut groups: HashMap<(i32, i32, i32), usize> = HashMap::new();
+        for &seed in sample {
+            *groups.entry(rng.levels(seed, shelves)).or_default() += 1;
+        }
+        // summed as integers so the order of the groups doesn't change the result
+        let same: u64 = groups.values().map(|&x| x as u64 * x as u64).sum();
+        let len = sample.len() as f64;
+        same as f64 / (len * len) * self.possible_seeds.len() as f64
+    }
+
+    /// The XP seed, only once a single candidate is left
+    pub fn seed(&self) -> Result<i32, Error> {
+        match self.possible_seeds.len() {
+            0 => Err(Error::EmptyCandidateSet),
+            1 => Ok(self.possible_seeds.iter().next().unwrap()),
+            n => Err(Error::AmbiguousSeed(n)),
+        }
     }
 
-    #[cfg(not(feature = "threads"))]
-    #[wasm_bindgen(js_name = firstInput)]
     pub fn first_input(&mut self, info: EnchantmentTableInfo, info2: EnchantmentTableInfo) {
-        for seed in self.start_size.clone() {
-            if self.rng.verify_seed(seed, info.into()) && self.rng.verify_seed(seed, info2.into()) {
-                self.possible_seeds.push(seed);
+        // can't fail because nothing can cancel it
+        let _ = self.first_input_with(info, info2, |_| {}, &CancellationToken::new());
+    }
+
+    /// Same as `first_input` but reports progress every `PROGRESS_CHUNK` seeds, if it gets
+    /// cancelled the cracker is left as it was before
+    pub fn first_input_with(
+        &mut self,
+        info: EnchantmentTableInfo,
+        info2: EnchantmentTableInfo,
+        progress: impl FnMut(Progress),
+        cancel: &CancellationToken,
+    ) -> Result<(), Error> {
+        self.crack_all_with(&[info, info2], progress, cancel)
+    }
+
+    /// Instead of keeping every seed that matches the first two inputs and filtering them with
+    /// `add_input`, checks every input on each seed in a single pass so only the seeds that match
+    /// all of them ever get stored
+    pub fn crack_all(&mut self, infos: &[EnchantmentTableInfo]) -> Result<(), Error> {
+        self.crack_all_with(infos, |_| {}, &CancellationToken::new())
+    }
+
+    /// Same as `crack_all` but reports progress every `PROGRESS_CHUNK` seeds, if it gets
+    /// cancelled the cracker is left as it was before
+    pub fn crack_all_with(
+        &mut self,
+        infos: &[EnchantmentTableInfo],
+        progress: impl FnMut(Progress),
+        cancel: &CancellationToken,
+    ) -> Result<(), Error> {
+        let mut cursor = self.cursor(infos)?;
+        self.resume_with(&mut cursor, progress, cancel)
+    }
+
+    /// A cursor over the seeds this cracker is in charge of
+    #[cfg(not(feature = "parallel"))]
+    pub fn cursor(&self, infos: &[EnchantmentTableInfo]) -> Result<SearchCursor, Error> {
+        SearchCursor::with_range(infos, self.start_size.clone())
+    }
+
+    /// A cursor over the seeds this cracker is in charge of
+    #[cfg(feature = "parallel")]
+    pub fn cursor(&self, infos: &[EnchantmentTableInfo]) -> Result<SearchCursor, Error> {
+        SearchCursor::new(infos)
+    }
+
+    /// Checks the next `len` seeds of the cursor and moves it forward, the cursor doesn't need
+    /// to come from this cracker
+    pub fn resume(&mut self, cursor: &mut SearchCursor, len: u32) {
+        let range = cursor.advance(len);
+        let found = self.search_range(cursor.inputs(), range);
+        self.add_candidates(found);
+    }
+
+    /// Checks every seed left in the cursor and reports progress every `PROGRESS_CHUNK` seeds, if
+    /// it gets cancelled neither the cracker nor the cursor move
+    pub fn resume_with(
+        &mut self,
+        cursor: &mut SearchCursor,
+        mut progress: impl FnMut(Progress),
+        cancel: &CancellationToken,
+    ) -> Result<(), Error> {
+        let mut next = cursor.clone();
+        // only added once the whole range is done
+        let mut found = Vec::new();
+        while !next.is_done() {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
             }
+            let range = next.advance(PROGRESS_CHUNK);
+            found.append(&mut self.search_range(next.inputs(), range));
+            progress(Progress {
+                done: next.progress(),
+                found: self.possible_seeds.len() + found.len(),
+            });
         }
+        *cursor = next;
+        self.add_candidates(found);
+        Ok(())
+    }
 
-        if self.thread_id == self.threads - 1
-            && self.rng.verify_seed(i32::MAX, info.into())
-            && self.rng.verify_seed(i32::MAX, info2.into())
-        {
-            self.possible_seeds.push(i32::MAX);
+    /// Checks `len` seeds starting from `start` against every input, stops after `i32::MAX`
+    pub fn search_chunk(
+        &mut self,
+        infos: &[EnchantmentTableInfo],
+        start: i32,
+        len: u32,
+    ) -> Result<(), Error> {
+        if infos.is_empty() {
+            return Err(Error::NoInputs);
+        }
+        let start = start as i64;
+        let end = cmp::min(start + len as i64, SEED_SPACE.end);
+        let found = self.search_range(infos, start..end);
+        self.add_candidates(found);
+        Ok(())
+    }
+
+    /// The seeds of `range` that match every input, without adding them
+    #[cfg(feature = "parallel")]
+    fn search_range(&self, infos: &[EnchantmentTableInfo], range: Range<i64>) -> Vec<i32> {
+        let alternatives = alternatives(infos);
+        let constraints = BitConstraints::with_alternatives(&alternatives);
+        let pieces: Vec<i64> = range.clone().step_by(SEARCH_PIECE).collect();
+        let search = || -> Vec<i32> {
+            pieces
+                .into_par_iter()
+                .flat_map_iter(|start| {
+                    let mut filter = BatchFilter::with_alternatives(&alternatives);
+                    let end = cmp::min(start + SEARCH_PIECE as i64, range.end);
+                    constraints.for_each(start..end, |x| filter.push(x));
+                    filter.finish()
+                })
+                .collect()
+        };
+        match &self.pool {
+            Some(pool) => pool.install(search),
+            None => search(),
         }
     }
 
-    #[wasm_bindgen(js_name = addInput)]
+    /// The seeds of `range` that match every input, without adding them
+    #[cfg(not(feature = "parallel"))]
+    fn search_range(&self, infos: &[EnchantmentTableInfo], range: Range<i64>) -> Vec<i32> {
+        let alternatives = alternatives(infos);
+        let mut filter = BatchFilter::with_alternatives(&alternatives);
+        BitConstraints::with_alternatives(&alternatives).for_each(range, |seed| filter.push(seed));
+        filter.finish()
+    }
+
     pub fn add_input(&mut self, info: EnchantmentTableInfo) {
-        let rng = &mut self.rng;
-        self.possible_seeds
-            .retain(|&x| rng.verify_seed(x, info.into()));
+        self.push_observation(Observation::Input(info));
+    }
+
+    /// Keeps the seeds that show `enchantment` at `level` when hovering over a zero-indexed slot,
+    /// `info` is what the table showed at the same time
+    pub fn add_clue(
+        &mut self,
+        item: Item,
+        slot: i32,
+        info: EnchantmentTableInfo,
+        enchantment: Enchantment,
+        level: i32,
+        version: Version,
+    ) -> Result<(), Error> {
+        let observation = Observation::Clue {
+            item,
+            slot,
+            info,
+            enchantment,
+            level,
+            version,
+        };
+        observation.validate()?;
+        self.push_observation(observation);
+        Ok(())
+    }
+
+    /// Every observation the candidates went through, in order
+    pub fn observations(&self) -> &[Observation] {
+        &self.history
+    }
+
+    /// Takes back the last observation, `false` if there was none
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(observation) => {
+                self.undone.push(observation);
+                self.replay();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the last observation taken back by `undo` again, `false` if there was none
+    pub fn redo(&mut self) -> bool {
+        match self.undone.pop() {
+            Some(observation) => {
+                self.apply(&observation);
+                self.history.push(observation);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Takes back any observation, the ones after it move one place down
+    pub fn remove_observation(&mut self, index: usize) -> Result<Observation, Error> {
+        if index >= self.history.len() {
+            return Err(Error::NoObservation(index));
+        }
+        let observation = self.history.remove(index);
+        self.undone.clear();
+        self.replay();
+        Ok(observation)
+    }
+
+    /// Swaps an observation for a fixed one and gives back the old one
+    pub fn replace_observation(
+        &mut self,
+        index: usize,
+        observation: Observation,
+    ) -> Result<Observation, Error> {
+        observation.validate()?;
+        let old = self
+            .history
+            .get_mut(index)
+            .ok_or(Error::NoObservation(index))?;
+        let old = std::mem::replace(old, observation);
+        self.undone.clear();
+        self.replay();
+        Ok(old)
+    }
+
+    /// Filters the candidates from before the first observation with the whole history again,
+    /// nothing has to be searched again
+    pub fn replay(&mut self) {
+        match &self.snapshot {
+            Some(snapshot) => self.possible_seeds = snapshot.clone(),
+            None => return,
+        }
+        self.mismatches.clear();
+        let history = std::mem::take(&mut self.history);
+        for observation in history.iter() {
+            self.apply(observation);
+            self.history.push(observation.clone());
+        }
+    }
+
+    fn push_observation(&mut self, observation: Observation) {
+        if self.snapshot.is_none() {
+            self.snapshot = Some(self.possible_seeds.clone());
+        }
+        self.undone.clear();
+        self.apply(&observation);
+        self.history.push(observation);
+    }
+
+    /// Counts the next observation against the candidates that don't match it and drops the
+    /// ones that go over the tolerance
+    fn apply(&mut self, observation: &Observation) {
+        let (rng, mismatches) = (&mut self.rng, &mut self.mismatches);
+        let (tolerance, index) = (self.tolerance, self.history.len());
+        self.possible_seeds.retain(|x| {
+            if observation.matches(rng, x) {
+                return true;
+            }
+            if mismatches.get(&x).map_or(0, Vec::len) >= tolerance {
+                mismatches.remove(&x);
+                return false;
+            }
+            mismatches.entry(x).or_default().push(index);
+            true
+        });
+    }
+
+    /// Seeds found after some observations go through all of them right away, and in the
+    /// snapshot too so a replay keeps them
+    fn add_candidates(&mut self, seeds: impl IntoIterator<Item = i32>) {
+        let snapshot = match &mut self.snapshot {
+            Some(snapshot) => snapshot,
+            None => return self.possible_seeds.extend(seeds),
+        };
+        let mut seeds: Vec<i32> = seeds.into_iter().collect();
+        snapshot.extend(seeds.iter().copied());
+        let (rng, mismatches) = (&mut self.rng, &mut self.mismatches);
+        let (history, tolerance) = (&self.history, self.tolerance);
+        seeds.retain(|&x| {
+            let missed: Vec<usize> = (0..history.len())
+                .filter(|&i| !history[i].matches(rng, x))
+                .collect();
+            match missed.len() {
+                0 => true,
+                n if n > tolerance => false,
+                _ => {
+                    mismatches.insert(x, missed);
+                    true
+                }
+            }
+        });
+        self.possible_seeds.extend(seeds);
     }
 
     pub fn contains(&self, x: i32) -> bool {
-        self.possible_seeds.iter().any(|&y| x == y)
+        self.possible_seeds.contains(x)
+    }
+
+    /// Every candidate, from the lowest to the highest
+    pub fn candidates(&self) -> candidates::Iter<'_> {
+        self.possible_seeds.iter()
+    }
+
+    /// Up to `len` candidates starting from the one at `offset`, in the order of `candidates`
+    pub fn candidate_page(&self, offset: usize, len: usize) -> Vec<i32> {
+        self.possible_seeds.iter().skip(offset).take(len).collect()
+    }
+
+    /// Compact binary form of the candidates, see `candidates::encode`
+    pub fn export_candidates(&self) -> Vec<u8> {
+        self.possible_seeds.encode()
+    }
+
+    pub fn export_candidates_json(&self) -> String {
+        self.possible_seeds.encode_json()
+    }
+
+    /// Adds the candidates to the ones already in the cracker, so importing the export of every
+    /// shard gives back the whole set
+    pub fn import_candidates(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.add_candidates(candidates::decode(bytes)?);
+        Ok(())
+    }
+
+    pub fn import_candidates_json(&mut self, json: &str) -> Result<(), Error> {
+        self.add_candidates(candidates::decode_json(json)?);
+        Ok(())
+    }
+
+    /// Whether `merge` would take `other`, the observations of one shard have to start with the
+    /// ones of the other
+    pub fn can_merge(&self, other: &Cracker) -> bool {
+        let shared = cmp::min(self.history.len(), other.history.len());
+        self.history[..shared] == other.history[..shared]
+    }
+
+    /// Moves every candidate of another shard into this one and leaves `other` empty, the range
+    /// this cracker is in charge of grows to cover both shards. The shard that's behind goes
+    /// through the observations it's missing first, nothing is moved if `can_merge` fails
+    pub fn merge(&mut self, other: &mut Cracker) -> Result<(), Error> {
+        if !self.can_merge(other) {
+            return Err(Error::DifferentHistories);
+        }
+        self.catch_up(&other.history);
+        other.catch_up(&self.history);
+        #[cfg(not(feature = "parallel"))]
+        {
+            self.start_size = cmp::min(self.start_size.start, other.start_size.start)
+                ..cmp::max(self.start_size.end, other.start_size.end);
+        }
+        if self.snapshot.is_some() || other.snapshot.is_some() {
+            let mut snapshot = self
+                .snapshot
+                .take()
+                .unwrap_or_else(|| self.possible_seeds.clone());
+            let other_snapshot = other.snapshot.as_ref().unwrap_or(&other.possible_seeds);
+            snapshot.extend(other_snapshot.iter());
+            self.snapshot = Some(snapshot);
+        }
+        self.possible_seeds.extend(other.possible_seeds.iter());
+        // both histories are the same by now so the indices mean the same observations
+        self.mismatches.append(&mut other.mismatches);
+        other.reset();
+        Ok(())
+    }
+
+    /// Applies the observations of `history` past the ones this cracker already went through
+    fn catch_up(&mut self, history: &[Observation]) {
+        for observation in history.iter().skip(self.history.len()) {
+            self.push_observation(observation.clone());
+        }
+    }
+
+    /// Merges every shard into a single cracker, `add_input` and `seed` then work over the
+    /// candidates of all of them
+    pub fn from_shards(shards: impl IntoIterator<Item = Cracker>) -> Result<Cracker, Error> {
+        let mut shards = shards.into_iter();
+        let mut cracker = shards.next().ok_or(Error::NoShards)?;
+        for mut shard in shards {
+            cracker.merge(&mut shard)?;
+        }
+        Ok(cracker)
+    }
+}
+
+/// Every alternative of every input, for the searches
+fn alternatives(infos: &[EnchantmentTableInfo]) -> Vec<Vec<(i32, i32, i32, i32)>> {
+    infos
+        .iter()
+        .map(|info| info.alternatives().collect())
+        .collect()
+}
+
+#[cfg(feature = "parallel")]
+impl Default for Cracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the player has to do to get the wanted enchantments, slots are zero-indexed
+#[derive(Debug, PartialEq, Clone)]
+pub enum SimulationResult {
+    /// No plan was found within the amount of item throws that get simulated
+    NotFound,
+    /// The enchantments are already in the table
+    Direct {
+        slot: i32,
+        shelves: i32,
+        enchantments: Vec<EnchantmentInstance>,
+        level: i32,
+    },
+    /// Throw out `throws` items, do a dummy enchantment and then enchant
+    AfterThrows {
+        throws: i32,
+        slot: i32,
+        shelves: i32,
+        enchantments: Vec<EnchantmentInstance>,
+        level: i32,
+    },
+}
+
+impl SimulationResult {
+    /// Same as the original EnchCracker, -2: not found; -1: no dummy enchantment needed;
+    /// >= 0: number of times needed to throw out item before dummy enchantment
+    pub fn times_needed(&self) -> i32 {
+        match self {
+            SimulationResult::NotFound => -2,
+            SimulationResult::Direct { .. } => -1,
+            SimulationResult::AfterThrows { throws, .. } => *throws,
+        }
     }
 }
 
@@ -169,91 +833,112 @@ impl ItemInstance {
     }
 }
 
-#[wasm_bindgen]
+/// The only player seed left, if there's exactly one
+fn single_seed(seeds: Vec<u64>) -> Result<u64, Error> {
+    match seeds[..] {
+        [] => Err(Error::NoSeedFound),
+        [x] => Ok(x),
+        _ => Err(Error::AmbiguousSeed(seeds.len())),
+    }
+}
+
 pub struct Manipulator {
-    player_seed: u64,
+    player: Lcg48,
     items: EnumMap<Item, ItemInstance>,
 }
 
-#[wasm_bindgen]
 impl Manipulator {
-    #[wasm_bindgen(constructor)]
-    pub fn new(seed1: u32, seed2: u32) -> Option<Manipulator> {
-        match Self::calculate_seed(seed1, seed2) {
-            Some(player_seed) => Some(Self {
-                player_seed,
-                items: Default::default(),
-            }),
-            None => None,
-        }
-    }
-
-    fn calculate_seed(seed1: u32, seed2: u32) -> Option<u64> {
-        let seed1_high = ((seed1 as u64) << 16) & 0x0000_FFFF_FFFF_0000;
-        let seed2_high = ((seed2 as u64) << 16) & 0x0000_FFFF_FFFF_0000;
-
-        for seed1_low in 0..65536 {
-            let part: u64 =
-                (Wrapping(seed1_high | seed1_low) * Wrapping(0x5DEECE66D) + Wrapping(0xB)).0;
-            if (part & 0x0000_FFFF_FFFF_0000) == seed2_high {
-                return Some(part & 0x0000_FFFF_FFFF_FFFF);
-            }
-        }
-        None
+    /// Fails with `AmbiguousSeed` if more than one player seed gives both XP seeds, a third one
+    /// can tell them apart with `from_xp_seeds`
+    pub fn new(seed1: u32, seed2: u32) -> Result<Manipulator, Error> {
+        let player_seed = single_seed(Self::calculate_seeds(seed1, seed2))?;
+        Ok(Self {
+            player: Lcg48::new(player_seed),
+            items: Default::default(),
+        })
     }
 
-    #[wasm_bindgen(js_name = changeSeed)]
-    pub fn change_seed(&mut self, seed1: u32, seed2: u32) -> bool {
-        match Self::calculate_seed(seed1, seed2) {
-            Some(new_seed) => {
-                self.player_seed = new_seed;
-                true
-            }
-            None => false,
-        }
+    /// For XP seeds that weren't given out one right after the other or for more than two of
+    /// them, see `player::player_seeds`
+    pub fn from_xp_seeds(first: u32, rest: &[(Gap, u32)]) -> Result<Manipulator, Error> {
+        let player_seed = single_seed(player::player_seeds(first, rest))?;
+        Ok(Self {
+            player: Lcg48::new(player_seed),
+            items: Default::default(),
+        })
     }
 
-    #[wasm_bindgen(getter = playerSeed)]
-    pub fn player_seed(&self) -> js_sys::Uint8Array {
-        let array = js_sys::Uint8Array::new_with_length(6);
-        let bytes = self.player_seed.to_le_bytes();
-        for i in (0..array.length()).rev() {
-            array.set_index(i, bytes[i as usize]);
-        }
-        array
+    /// Every player seed that gives `seed2` right after `seed1`. No two guesses for the low bits
+    /// of `seed1` end up with the same top bits one step later, so there's never more than one,
+    /// the ambiguous cases come from gaps that aren't known
+    pub fn calculate_seeds(seed1: u32, seed2: u32) -> Vec<u64> {
+        player::player_seeds(seed1, &[(Gap::Steps(1), seed2)])
+    }
+
+    pub fn change_seed(&mut self, seed1: u32, seed2: u32) -> Result<(), Error> {
+        self.player = Lcg48::new(single_seed(Self::calculate_seeds(seed1, seed2))?);
+        Ok(())
+    }
+
+    /// The 48-bit player seed, as used by Java's `Random`
+    pub fn player_seed(&self) -> u64 {
+        self.player.state()
     }
 
-    #[wasm_bindgen]
     pub fn simulate(
-        &mut self,
+        &self,
         item: Item,
         max_shelves: i32,
         player_level: i32,
         version: Version,
-    ) -> Option<js_sys::Int32Array> {
-        let mut seed = self.player_seed;
-        let array = js_sys::Int32Array::new_with_length(3);
+    ) -> Result<SimulationResult, Error> {
+        if !(0..=15).contains(&max_shelves) {
+            return Err(Error::InvalidShelves(max_shelves));
+        }
+        let shelves: Vec<i32> = (0..=max_shelves).collect();
+        self.simulate_with_shelves(item, &shelves, player_level, version)
+    }
+
+    /// Same as `simulate` but it only tries those amounts of bookshelves, from the fewest to the
+    /// most, for tables that can't be rearranged. `[15]` is a table that's always full and
+    /// `[0, 15]` one where something blocks every bookshelf at once
+    pub fn simulate_with_shelves(
+        &self,
+        item: Item,
+        shelves: &[i32],
+        player_level: i32,
+        version: Version,
+    ) -> Result<SimulationResult, Error> {
+        let mut player = self.player;
+        if shelves.is_empty() {
+            return Err(Error::NoShelves);
+        }
+        if let Some(&x) = shelves.iter().find(|x| !(0..=15).contains(*x)) {
+            return Err(Error::InvalidShelves(x));
+        }
+        let mut shelves = shelves.to_vec();
+        shelves.sort_unstable();
+        shelves.dedup();
+        if version.before(item.get_introduced_version()) {
+            return Err(Error::UnsupportedVersion(item, version));
+        }
         if self.items[item].enchantments.is_empty() {
-            return None;
-        }
-        // same as original EnchCracker
-        // -2: not found; -1: no dummy enchantment needed; >= 0: number of times needed
-        // to throw out item before dummy enchantment
-        let mut times_needed = -2;
-        let mut bookshelves_needed = 0;
-        let mut slot = 0;
+            return Err(Error::NoWantedEnchantments(item));
+        }
         let mut enchant_levels = [0; 3];
 
-        'outerLoop: for i in -1..=(64 * 32) {
+        // i == -1 means no dummy enchantment, otherwise it's the amount of items to throw out
+        // before the dummy enchantment
+        for i in -1..=(64 * 32) {
             let xp_seed = {
                 let unsigned = if i == -1 {
                     // XP seed will be the current seed, because there is no dummy enchant
-                    seed >> 16
+                    player.state() >> 16
                 } else {
                     // XP seed will be the current seed, advanced by one because of the dummy enchant
-                    ((Wrapping(seed) * Wrapping(0x5DEECE66D) + Wrapping(0xB)).0
-                        & 0x0000_FFFF_FFFF_FFFF)
-                        >> 16
+                    let mut dummy = player;
+                    dummy.step();
+                    dummy.state() >> 16
                 };
 
                 //check if its a negative number
@@ -263,10 +948,9 @@ impl Manipulator {
                     unsigned
                 }
             };
-            let mut rand = java_rand::Random::new(0);
-            for bookshelves in 0..=max_shelves {
-                bookshelves_needed = bookshelves;
-                rand.set_seed(xp_seed);
+            let mut rand = SimpleRandom::new(0);
+            for &bookshelves in shelves.iter() {
+                rand.set_seed(xp_seed as i64);
 
                 //Calculate all slot levels
                 for (j, original) in enchant_levels.iter_mut().enumerate() {
@@ -276,7 +960,7 @@ impl Manipulator {
                         num,
                         bookshelves,
                         item,
-                    );
+                    )?;
                     if level < num + 1 {
                         level = 0;
                     }
@@ -284,13 +968,13 @@ impl Manipulator {
                 }
 
                 'slotLoop: for (j, level) in enchant_levels.iter().enumerate() {
-                    slot = j as i32;
+                    let slot = j as i32;
                     // Get enchantments (changes RNG seed)
                     let enchantments = Enchantment::get_enchantments_in_table(
                         &mut rand,
                         xp_seed as i32,
                         item,
-                        j as i32,
+                        slot,
                         *level,
                         version,
                     );
@@ -337,128 +1021,67 @@ impl Manipulator {
                         }
                     }
 
-                    times_needed = i;
-                    break 'outerLoop;
+                    let level = *level;
+                    return Ok(if i == -1 {
+                        SimulationResult::Direct {
+                            slot,
+                            shelves: bookshelves,
+                            enchantments,
+                            level,
+                        }
+                    } else {
+                        SimulationResult::AfterThrows {
+                            throws: i,
+                            slot,
+                            shelves: bookshelves,
+                            enchantments,
+                            level,
+                        }
+                    });
                 }
             }
 
             //Simulate item throws
             if i != -1 {
-                for _j in 0..4 {
-                    seed = (Wrapping(seed) * Wrapping(0x5DEECE66D) + Wrapping(0xB)).0
-                        & 0x0000_FFFF_FFFF_FFFF;
-                }
+                player.skip(4);
             }
         }
 
-        array.set_index(0, times_needed);
-        array.set_index(1, slot + 1);
-        array.set_index(2, bookshelves_needed);
-        Some(array)
+        Ok(SimulationResult::NotFound)
     }
 
-    #[wasm_bindgen(js_name = updateSeed)]
-    pub fn update_seed(&mut self, times_needed: i32, chosen_slot: i32, player_level: i32) -> i32 {
+    /// `chosen_slot` is zero-indexed like the one in `SimulationResult`, -1 meaning that nothing
+    /// was enchanted
+    pub fn update_seed(
+        &mut self,
+        times_needed: i32,
+        chosen_slot: i32,
+        player_level: i32,
+    ) -> Result<i32, Error> {
         if times_needed == -2 || chosen_slot == -1 {
-            return player_level;
+            return Ok(player_level);
+        }
+        if !(0..=2).contains(&chosen_slot) {
+            return Err(Error::InvalidSlot(chosen_slot));
         }
 
         if times_needed != -1 {
-            //items thrown
-            for _i in 0..times_needed {
-                for _j in 0..4 {
-                    self.player_seed = (Wrapping(self.player_seed) * Wrapping(0x5DEECE66D)
-                        + Wrapping(0xB))
-                    .0 & 0x0000_FFFF_FFFF_FFFF;
-                }
-            }
+            //items thrown, 4 steps each
+            self.player.skip(4 * cmp::max(times_needed, 0) as u64);
             //dummy enchantment
-            self.player_seed = (Wrapping(self.player_seed) * Wrapping(0x5DEECE66D) + Wrapping(0xB))
-                .0
-                & 0x0000_FFFF_FFFF_FFFF;
+            self.player.step();
         }
         //actual enchantment
-        self.player_seed = (Wrapping(self.player_seed) * Wrapping(0x5DEECE66D) + Wrapping(0xB)).0
-            & 0x0000_FFFF_FFFF_FFFF;
+        self.player.step();
 
-        player_level - chosen_slot + (if times_needed != -1 { -1 } else { 0 })
+        Ok(player_level - (chosen_slot + 1) + (if times_needed != -1 { -1 } else { 0 }))
     }
 
-    #[wasm_bindgen(js_name = updateItem)]
     pub fn update_item(&mut self, item: Item, ench: &EnchantmentInstance) {
         self.items[item].update(ench);
     }
 
-    #[wasm_bindgen]
     pub fn reset(&mut self, item: Item) {
         self.items[item].reset();
     }
 }
-
-// this is is an struct because yay js util class! (not very rusty, ikr)
-// I do all this because you cant put methods into enums shared to js
-#[wasm_bindgen]
-pub struct Utilities;
-
-#[wasm_bindgen]
-impl Utilities {
-    fn get_introduced_version(thing: &dyn Introduced) -> Version {
-        //Traits are not supported yet for wasm
-        thing.get_introduced_version()
-    }
-
-    #[wasm_bindgen(js_name = materialIntroducedVersion)]
-    pub fn material_introduced_version(mat: Material) -> Version {
-        Self::get_introduced_version(&mat)
-    }
-
-    #[wasm_bindgen(js_name = itemIntroducedVersion)]
-    pub fn item_introduced_version(item: Item) -> Version {
-        Self::get_introduced_version(&item)
-    }
-
-    #[wasm_bindgen(js_name = enchantmentIntroducedVersion)]
-    pub fn enchantment_introduced_version(ench: Enchantment) -> Version {
-        Self::get_introduced_version(&ench)
-    }
-
-    #[wasm_bindgen(js_name = getMaxLevelInTable)]
-    pub fn get_max_level_in_table(ench: Enchantment, item: Item) -> i32 {
-        ench.get_max_level_in_table(item)
-    }
-
-    #[wasm_bindgen(js_name = areEnchantmentsCompatible)]
-    pub fn are_enchantments_compatible(
-        ench1: Enchantment,
-        ench2: Enchantment,
-        version: Version,
-    ) -> bool {
-        ench1.is_compatible_with(ench2, version)
-    }
-
-    #[wasm_bindgen(js_name = getEnchantments)]
-    pub fn get_enchantments(item: Item) -> js_sys::Uint8Array {
-        let iter = Enchantment::iter()
-            .filter(|x| x.can_apply(item, true) && !x.is_treasure())
-            .map(|x| x as u8);
-        let arr = js_sys::Uint8Array::new_with_length(iter.clone().count() as u32);
-        for (i, ench) in iter.enumerate() {
-            arr.set_index(i as u32, ench);
-        }
-        arr
-    }
-
-    #[wasm_bindgen(js_name = isTreasure)]
-    pub fn is_treasure(ench: Enchantment) -> bool {
-        ench.is_treasure()
-    }
-
-    #[wasm_bindgen(js_name = getItems)]
-    pub fn get_items(material: Material) -> js_sys::Uint8Array {
-        let arr = js_sys::Uint8Array::new_with_length(SET_MATERIAL as u32);
-        for (i, item) in material.get_items().iter().enumerate() {
-            arr.set_index(i as u32, *item as u8);
-        }
-        arr
-    }
-}
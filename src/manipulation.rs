@@ -1,10 +1,13 @@
+use crate::random::JavaRandom;
+use crate::Error;
 use enum_map::Enum;
 use std::cmp;
 use strum::IntoEnumIterator;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-#[wasm_bindgen]
-#[derive(Copy, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
 pub enum Version {
     V1_8,
     V1_9,
@@ -34,7 +37,7 @@ pub trait Introduced {
     fn get_introduced_version(&self) -> Version;
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(AsRefStr, EnumIter)]
 pub enum Material {
     Netherite,
@@ -116,7 +119,7 @@ impl Introduced for Material {
 
 // I think the probability of this getting optimized by the compiler is low but who cares
 // and maybe im wrong and the compiler is smarter than im (surely it is)
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(AsRefStr, EnumIter, PartialEq, Copy, Clone, Enum, Debug)]
 // I tried to do cfg_attr(test, derive(Debug)) but it didn't work?? I misunderstood something for sure...
 pub enum Item {
@@ -287,6 +290,13 @@ impl Item {
         }
     }
 
+    /// Enchantments that can show up for this item in an enchanting table
+    pub fn get_enchantments(&self) -> Vec<Enchantment> {
+        Enchantment::iter()
+            .filter(|x| x.can_apply(*self, true) && !x.is_treasure())
+            .collect()
+    }
+
     fn get_material(&self) -> Option<Material> {
         Material::iter().find(|x| x.has_item(self, true))
     }
@@ -306,8 +316,8 @@ impl Introduced for Item {
     }
 }
 
-#[wasm_bindgen]
-#[derive(PartialEq, Copy, Clone, EnumIter)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(PartialEq, Copy, Clone, EnumIter, Debug)]
 pub enum Enchantment {
     Protection,
     FireProtection,
@@ -431,10 +441,7 @@ impl Enchantment {
             }
             Enchantment::Respiration | Enchantment::AquaAffinity => item.is_helmet(),
             Enchantment::BindingCurse => {
-                item.is_armor()
-                    || [Item::Pumpkin, Item::Elytra, Item::Skull]
-                        .iter()
-                        .any(|x| *x == item)
+                item.is_armor() || [Item::Pumpkin, Item::Elytra, Item::Skull].contains(&item)
             }
             Enchantment::Sharpness | Enchantment::Smite | Enchantment::BaneOfArthropods => {
                 item.is_sword() || (!primary && item.is_axe())
@@ -683,22 +690,24 @@ impl Enchantment {
     }
 
     pub fn calc_enchantment_table_level(
-        rand: &mut java_rand::Random,
+        rand: &mut impl JavaRandom,
         slot: i32,
         bookshelves: i32,
         item: Item,
-    ) -> i32 {
+    ) -> Result<i32, Error> {
+        if !(0..=2).contains(&slot) {
+            return Err(Error::InvalidSlot(slot));
+        }
         if item.get_enchantability() == 0 {
-            return 0;
+            return Ok(0);
         }
         let level =
-            rand.next_i32_bound(8) + 1 + (bookshelves >> 1) + rand.next_i32_bound(bookshelves + 1);
-        match slot {
+            rand.next_int_bound(8) + 1 + (bookshelves >> 1) + rand.next_int_bound(bookshelves + 1);
+        Ok(match slot {
             0 => cmp::max(level / 3, 1),
             1 => level * 2 / 3 + 1,
-            2 => cmp::max(level, bookshelves * 2),
-            _ => panic!("More than 3 enchantment slots?"),
-        }
+            _ => cmp::max(level, bookshelves * 2),
+        })
     }
 
     pub fn get_highest_allowed_enchantments(
@@ -733,7 +742,7 @@ impl Enchantment {
     }
 
     pub fn add_random_enchantments(
-        rand: &mut java_rand::Random,
+        rand: &mut impl JavaRandom,
         item: Item,
         level: i32,
         treasure: bool,
@@ -747,9 +756,9 @@ impl Enchantment {
         }
 
         level += 1
-            + rand.next_i32_bound(enchantability / 4 + 1)
-            + rand.next_i32_bound(enchantability / 4 + 1);
-        let percent_change: f32 = (rand.next_f32() + rand.next_f32() - 1f32) * 0.15;
+            + rand.next_int_bound(enchantability / 4 + 1)
+            + rand.next_int_bound(enchantability / 4 + 1);
+        let percent_change: f32 = (rand.next_float() + rand.next_float() - 1f32) * 0.15;
         level += (level as f32 * percent_change).round() as i32;
         if level < 1 {
             level = 1;
@@ -767,7 +776,7 @@ impl Enchantment {
             enchs.push(ench)
         }
 
-        while rand.next_i32_bound(50) <= level {
+        while rand.next_int_bound(50) <= level {
             if version == Version::V1_14 {
                 level = level * 4 / 5 + 1;
                 allowed_enchs =
@@ -795,31 +804,49 @@ impl Enchantment {
     }
 
     pub fn get_enchantments_in_table(
-        rand: &mut java_rand::Random,
+        rand: &mut impl JavaRandom,
         xp_seed: i32,
         item: Item,
         slot: i32,
         levels: i32,
         version: Version,
     ) -> Vec<EnchantmentInstance> {
-        rand.set_seed(xp_seed as u64 + slot as u64);
+        rand.set_seed(xp_seed as i64 + slot as i64);
         let mut v = Self::add_random_enchantments(rand, item, levels, false, version);
         if Item::Book == item && v.len() > 1 {
-            v.remove(rand.next_i32_bound(v.len() as i32) as usize);
+            v.remove(rand.next_int_bound(v.len() as i32) as usize);
         }
         v
     }
 
+    /// The enchantment the table shows when hovering over a slot, it's one of the enchantments
+    /// the slot gives picked with the same `rand`
+    pub fn get_enchantment_clue(
+        rand: &mut impl JavaRandom,
+        xp_seed: i32,
+        item: Item,
+        slot: i32,
+        levels: i32,
+        version: Version,
+    ) -> Option<EnchantmentInstance> {
+        let mut v = Self::get_enchantments_in_table(rand, xp_seed, item, slot, levels, version);
+        if v.is_empty() {
+            return None;
+        }
+        let index = rand.next_int_bound(v.len() as i32);
+        Some(v.swap_remove(index as usize))
+    }
+
     fn weighted_random<T>(
-        rand: &mut java_rand::Random,
+        rand: &mut impl JavaRandom,
         v: &mut Vec<T>,
         weight_extractor: &dyn Fn(&T) -> i32,
     ) -> Option<T> {
-        let mut weight = v.iter().map(|x| weight_extractor(x)).sum();
+        let mut weight = v.iter().map(weight_extractor).sum();
         if weight <= 0 {
             return None;
         }
-        weight = rand.next_i32_bound(weight);
+        weight = rand.next_int_bound(weight);
 
         let index = v.iter().position(|x| {
             weight -= weight_extractor(x);
@@ -848,16 +875,16 @@ impl Introduced for Enchantment {
     }
 }
 
-#[wasm_bindgen]
-#[derive(PartialEq, Clone)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct EnchantmentInstance {
     pub enchantment: Enchantment,
     pub level: i32,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl EnchantmentInstance {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(enchantment: Enchantment, level: i32) -> Self {
         EnchantmentInstance { enchantment, level }
     }
@@ -0,0 +1,60 @@
+//! Getting the state of the player's RNG back from the XP seeds it gave out. Each XP seed is the
+//! top 32 bits of a state, so only the low 16 bits of the first one have to be guessed and every
+//! XP seed after it rules out almost all of the guesses.
+use crate::utils::{Lcg48, MASK};
+
+/// Most steps an unknown gap can be, enough for every throw `Manipulator::simulate` can ask for
+/// plus the dummy enchantment and the enchantment itself
+pub const MAX_GAP: u32 = 4 * 64 * 32 + 2;
+
+/// How many times the player's RNG got used between two XP seeds
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Gap {
+    /// Exactly that many steps, it's 1 for two enchantments in a row
+    Steps(u32),
+    /// Anything from 1 to `MAX_GAP` steps
+    Unknown,
+}
+
+impl Gap {
+    /// Two enchantments with some items thrown out in between, each throw uses the RNG 4 times
+    pub fn after_throws(throws: u32) -> Self {
+        Gap::Steps(1 + 4 * throws)
+    }
+}
+
+/// Every state the player's RNG can be in right after giving out the last XP seed. `rest` has
+/// each XP seed after `first` with how many steps it came after the one before it
+pub fn player_seeds(first: u32, rest: &[(Gap, u32)]) -> Vec<u64> {
+    let mut states: Vec<u64> = (0..1 << 16).map(|low| (first as u64) << 16 | low).collect();
+    for &(gap, xp_seed) in rest {
+        let mut next = Vec::new();
+        match gap {
+            Gap::Steps(n) => {
+                let (mult, addend) = Lcg48::steps(n as u64);
+                for state in states {
+                    let state = state.wrapping_mul(mult).wrapping_add(addend) & MASK as u64;
+                    if state >> 16 == xp_seed as u64 {
+                        next.push(state);
+                    }
+                }
+            }
+            Gap::Unknown => {
+                for state in states {
+                    let mut rng = Lcg48::new(state);
+                    for _ in 0..MAX_GAP {
+                        rng.step();
+                        if rng.state() >> 16 == xp_seed as u64 {
+                            next.push(rng.state());
+                        }
+                    }
+                }
+            }
+        }
+        // different guesses can end up in the same state with unknown gaps
+        next.sort_unstable();
+        next.dedup();
+        states = next;
+    }
+    states
+}
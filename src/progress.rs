@@ -0,0 +1,33 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// How many seeds get checked between progress reports and cancellation checks
+pub const PROGRESS_CHUNK: u32 = 1 << 22;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Progress {
+    /// Fraction of the seeds that were already checked, from 0 to 1
+    pub done: f64,
+    /// Candidates found so far
+    pub found: usize,
+}
+
+/// Can be cloned and shared with other threads, cancelling any of the clones cancels all of them
+#[derive(Clone, Default, Debug)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,76 @@
+//! Java's `java.util.Random`. Everything is built on top of `next` so the code that rolls
+//! enchantments can take any RNG that behaves like it, `utils::SimpleRandom` is the one the crate
+//! uses but tests can wrap it to record or fake calls.
+
+/// 2^-53, what `nextDouble` scales its 53 bits by
+const DOUBLE_UNIT: f64 = 1.0 / (1u64 << 53) as f64;
+
+pub trait JavaRandom {
+    /// Scrambles the seed the same way `Random::setSeed` does
+    fn set_seed(&mut self, seed: i64);
+
+    /// The next `bits` random bits, at most 32 of them
+    fn next(&mut self, bits: u32) -> i32;
+
+    /// Where `next_gaussian` keeps the second number of each pair, `set_seed` has to clear it
+    fn next_next_gaussian(&mut self) -> &mut Option<f64>;
+
+    fn next_int(&mut self) -> i32 {
+        self.next(32)
+    }
+
+    /// Panics if `bound` isn't positive, java throws there
+    fn next_int_bound(&mut self, bound: i32) -> i32 {
+        assert!(bound > 0, "bound must be positive");
+        let mut r = self.next(31);
+        let m = bound - 1;
+        if bound & m == 0 {
+            return ((bound as i64 * r as i64) >> 31) as i32;
+        }
+        // values past the last multiple of `bound` get rolled again
+        let mut u = r;
+        loop {
+            r = u % bound;
+            if u.wrapping_sub(r).wrapping_add(m) >= 0 {
+                return r;
+            }
+            u = self.next(31);
+        }
+    }
+
+    fn next_long(&mut self) -> i64 {
+        let high = (self.next(32) as i64) << 32;
+        high.wrapping_add(self.next(32) as i64)
+    }
+
+    fn next_boolean(&mut self) -> bool {
+        self.next(1) != 0
+    }
+
+    fn next_float(&mut self) -> f32 {
+        self.next(24) as f32 / (1 << 24) as f32
+    }
+
+    fn next_double(&mut self) -> f64 {
+        let high = (self.next(26) as i64) << 27;
+        (high + self.next(27) as i64) as f64 * DOUBLE_UNIT
+    }
+
+    /// Java uses `StrictMath.log`, `ln` can be off by the last bit once in a while
+    fn next_gaussian(&mut self) -> f64 {
+        if let Some(x) = self.next_next_gaussian().take() {
+            return x;
+        }
+        let (v1, v2, s) = loop {
+            let v1 = 2.0 * self.next_double() - 1.0;
+            let v2 = 2.0 * self.next_double() - 1.0;
+            let s = v1 * v1 + v2 * v2;
+            if s < 1.0 && s != 0.0 {
+                break (v1, v2, s);
+            }
+        };
+        let multiplier = (-2.0 * s.ln() / s).sqrt();
+        *self.next_next_gaussian() = Some(v2 * multiplier);
+        v1 * multiplier
+    }
+}
@@ -0,0 +1,124 @@
+use crate::candidates::{read_varint, write_varint};
+use crate::{EnchantmentTableInfo, Error};
+use std::ops::Range;
+
+/// Every XP seed, as an `i64` range so that `i32::MAX` can be included
+pub const SEED_SPACE: Range<i64> = i32::MIN as i64..i32::MAX as i64 + 1;
+
+const INFO_BYTES: usize = 5;
+
+/// Where a first-input scan is at, it can be turned into bytes to pause the scan and resume it
+/// later, even on another worker
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchCursor {
+    infos: Vec<EnchantmentTableInfo>,
+    start: i64,
+    next: i64,
+    end: i64,
+}
+
+impl SearchCursor {
+    /// A cursor over every XP seed, seeds have to match all of the inputs
+    pub fn new(infos: &[EnchantmentTableInfo]) -> Result<Self, Error> {
+        Self::with_range(infos, SEED_SPACE)
+    }
+
+    /// A cursor over only some seeds, the range gets clamped to `SEED_SPACE`
+    pub fn with_range(infos: &[EnchantmentTableInfo], range: Range<i64>) -> Result<Self, Error> {
+        if infos.is_empty() {
+            return Err(Error::NoInputs);
+        }
+        let start = range.start.clamp(SEED_SPACE.start, SEED_SPACE.end);
+        let end = range.end.clamp(start, SEED_SPACE.end);
+        Ok(SearchCursor {
+            infos: infos.to_vec(),
+            start,
+            next: start,
+            end,
+        })
+    }
+
+    pub fn inputs(&self) -> &[EnchantmentTableInfo] {
+        &self.infos
+    }
+
+    /// The seeds that are left to check
+    pub fn remaining(&self) -> Range<i64> {
+        self.next..self.end
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.end
+    }
+
+    /// Fraction of the range that was already checked, from 0 to 1
+    pub fn progress(&self) -> f64 {
+        if self.end == self.start {
+            return 1.0;
+        }
+        (self.next - self.start) as f64 / (self.end - self.start) as f64
+    }
+
+    /// Takes the next `len` seeds out of the cursor
+    pub fn advance(&mut self, len: u32) -> Range<i64> {
+        let start = self.next;
+        self.next = (start + len as i64).min(self.end);
+        start..self.next
+    }
+
+    /// The amount of inputs as a varint, 5 bytes for each input and then the range as 3 little endian
+    /// `i64`s. An input is the possible bookshelves as a little endian bit set followed by the
+    /// three levels
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(10 + self.infos.len() * INFO_BYTES + 24);
+        write_varint(&mut bytes, self.infos.len() as u64);
+        for info in self.infos.iter() {
+            let (slot1, slot2, slot3) = info.levels();
+            bytes.extend_from_slice(&info.shelf_mask().to_le_bytes());
+            bytes.extend([slot1 as u8, slot2 as u8, slot3 as u8]);
+        }
+        for x in [self.start, self.next, self.end] {
+            bytes.extend_from_slice(&x.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut iter = bytes.iter().copied();
+        let count = read_varint(&mut iter).ok_or(Error::InvalidCursor)?;
+        let infos_start = bytes.len() - iter.len();
+        // don't multiply a length that can't be right, and every cursor has some inputs
+        if count == 0 || count > bytes.len() as u64 {
+            return Err(Error::InvalidCursor);
+        }
+        let ints_start = infos_start + count as usize * INFO_BYTES;
+        if bytes.len() != ints_start + 24 {
+            return Err(Error::InvalidCursor);
+        }
+        let infos = bytes[infos_start..ints_start]
+            .chunks(INFO_BYTES)
+            .map(|x| {
+                let mask = u16::from_le_bytes([x[0], x[1]]);
+                let (slot1, slot2, slot3) = (x[2] as i32, x[3] as i32, x[4] as i32);
+                let info = EnchantmentTableInfo::with_shelf_mask(mask, slot1, slot2, slot3)?;
+                // a mask that loses bookshelves wasn't written by `to_bytes`
+                match info.shelf_mask() == mask {
+                    true => Ok(info),
+                    false => Err(Error::InvalidCursor),
+                }
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let ints = &bytes[ints_start..];
+        let int = |i: usize| i64::from_le_bytes(ints[i * 8..i * 8 + 8].try_into().unwrap());
+        let (start, next, end) = (int(0), int(1), int(2));
+        if !(SEED_SPACE.start <= start && start <= next && next <= end && end <= SEED_SPACE.end) {
+            return Err(Error::InvalidCursor);
+        }
+        Ok(SearchCursor {
+            infos,
+            start,
+            next,
+            end,
+        })
+    }
+}
@@ -1,64 +1,156 @@
+use crate::batch;
+use crate::random::JavaRandom;
 use std::cmp;
 use std::num::Wrapping;
+use std::ops::{Range, RangeInclusive};
 
-const MULT: i64 = 0x5DEECE66D;
-const MASK: i64 = (1 << 48) - 1;
+/// Constants of java's LCG, every other module takes them from here
+pub(crate) const MULT: i64 = 0x5DEECE66D;
+pub(crate) const ADDEND: i64 = 0xB;
+pub(crate) const MASK: i64 = (1 << 48) - 1;
 
+/// A slot that wasn't seen, every real level is at least 1 and hidden slots read as 0 anyway
+pub const UNKNOWN_LEVEL: i32 = 0;
+
+/// Every level a zero-indexed slot can show with that amount of bookshelves, it's a range
+/// because `generic_enchantibility` can roll every value between its bounds
+pub fn level_range(shelves: i32, slot: i32) -> RangeInclusive<i32> {
+    let min = 1 + (shelves >> 1);
+    let max = 8 + (shelves >> 1) + shelves;
+    slot_level(slot, min, shelves)..=slot_level(slot, max, shelves)
+}
+
+/// If a zero-indexed slot can show that level, an unknown level can always be shown
+pub fn can_show(shelves: i32, slot: i32, level: i32) -> bool {
+    level == UNKNOWN_LEVEL || level_range(shelves, slot).contains(&level)
+}
+
+/// If a rolled level matches what was seen in the slot
+pub(crate) fn shows(level: i32, seen: i32) -> bool {
+    seen == UNKNOWN_LEVEL || level == seen
+}
+
+/// The level a zero-indexed slot shows for an enchantibility roll
+pub(crate) fn slot_level(slot: i32, enchantibility: i32, shelves: i32) -> i32 {
+    match slot {
+        0 => cmp::max(enchantibility / 3, 1),
+        1 => enchantibility * 2 / 3 + 1,
+        _ => cmp::max(enchantibility, shelves * 2),
+    }
+}
+
+/// Java's 48-bit LCG by itself, without any of the ways `Random` turns states into numbers. It
+/// can jump any amount of steps forwards or backwards at once
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Lcg48 {
+    state: u64,
+}
+
+impl Lcg48 {
+    /// Takes a state as it is, without the scrambling of `set_seed`
+    pub fn new(state: u64) -> Self {
+        Lcg48 {
+            state: state & MASK as u64,
+        }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// Multiplier and addend of `n` steps done at once, squaring the step so it only takes
+    /// `O(log n)`
+    pub fn steps(mut n: u64) -> (u64, u64) {
+        let mask = MASK as u64;
+        let (mut mult, mut addend) = (1u64, 0u64);
+        let (mut step_mult, mut step_addend) = (MULT as u64, ADDEND as u64);
+        while n > 0 {
+            if n & 1 == 1 {
+                mult = mult.wrapping_mul(step_mult) & mask;
+                addend = addend.wrapping_mul(step_mult).wrapping_add(step_addend) & mask;
+            }
+            step_addend = step_addend
+                .wrapping_mul(step_mult)
+                .wrapping_add(step_addend)
+                & mask;
+            step_mult = step_mult.wrapping_mul(step_mult) & mask;
+            n >>= 1;
+        }
+        (mult, addend)
+    }
+
+    pub fn step(&mut self) {
+        self.skip(1);
+    }
+
+    pub fn skip(&mut self, n: u64) {
+        let (mult, addend) = Self::steps(n);
+        self.state = self.state.wrapping_mul(mult).wrapping_add(addend) & MASK as u64;
+    }
+
+    /// Every state comes back after 2^48 steps, so going back is going forwards the rest of the
+    /// way
+    pub fn rewind(&mut self, n: u64) {
+        let period = MASK as u64 + 1;
+        self.skip(period - n % period);
+    }
+}
+
+/// The crate's own java `Random`, `Default` starts from a state of 0 without any scrambling
 #[derive(Default)]
 pub struct SimpleRandom {
     pub seed: i64,
+    next_next_gaussian: Option<f64>,
 }
 
-impl SimpleRandom {
-    pub fn set_seed(&mut self, seed: i64) {
+impl JavaRandom for SimpleRandom {
+    fn set_seed(&mut self, seed: i64) {
         self.seed = (seed ^ MULT) & MASK;
+        self.next_next_gaussian = None;
     }
 
-    pub fn next_int(&mut self) -> i32 {
-        self.seed = (Wrapping(self.seed) * Wrapping(MULT) + Wrapping(0xB)).0 & MASK;
-        (self.seed as u64 >> 17) as i32
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = (Wrapping(self.seed) * Wrapping(MULT) + Wrapping(ADDEND)).0 & MASK;
+        (self.seed as u64 >> (48 - bits)) as i32
     }
 
-    pub fn next_int_bound(&mut self, bound: Wrapping<i32>) -> i32 {
-        let mut r = Wrapping(self.next_int());
-        let m = bound - Wrapping(1);
-        if (bound & m) == Wrapping(0) {
-            r = Wrapping(((bound.0 as i64 * r.0 as i64) >> 31) as i32);
-        } else {
-            let mut u = r;
-            while {
-                r = u % bound;
-                u - r + m
-            }
-            .0 < 0
-            {
-                u = Wrapping(self.next_int());
-            }
-        }
-        r.0
+    fn next_next_gaussian(&mut self) -> &mut Option<f64> {
+        &mut self.next_next_gaussian
+    }
+}
+
+impl SimpleRandom {
+    /// Same as java's `new Random(seed)`
+    pub fn new(seed: i64) -> Self {
+        let mut rng: Self = Default::default();
+        rng.set_seed(seed);
+        rng
     }
 
     fn generic_enchantibility(&mut self, shelves: i32) -> i32 {
-        let first = self.next_int_bound(Wrapping(8));
-        let second = self.next_int_bound(Wrapping(shelves + 1));
+        let first = self.next_int_bound(8);
+        let second = self.next_int_bound(shelves + 1);
         first + 1 + (shelves >> 1) + second
     }
 
     fn levels_slot1(&mut self, shelves: i32) -> i32 {
-        let slot1 = self.generic_enchantibility(shelves) / 3;
-        if slot1 < 1 {
-            1
-        } else {
-            slot1
-        }
+        slot_level(0, self.generic_enchantibility(shelves), shelves)
     }
 
     fn levels_slot2(&mut self, shelves: i32) -> i32 {
-        (self.generic_enchantibility(shelves) * 2 / 3) + 1
+        slot_level(1, self.generic_enchantibility(shelves), shelves)
     }
 
     fn levels_slot3(&mut self, shelves: i32) -> i32 {
-        cmp::max(self.generic_enchantibility(shelves), shelves * 2)
+        slot_level(2, self.generic_enchantibility(shelves), shelves)
+    }
+
+    /// The levels the three slots show for a seed
+    pub fn levels(&mut self, seed: i32, shelves: i32) -> (i32, i32, i32) {
+        self.set_seed(seed as i64);
+        let slot1 = self.levels_slot1(shelves);
+        let slot2 = self.levels_slot2(shelves);
+        (slot1, slot2, self.levels_slot3(shelves))
     }
 
     pub fn verify_seed(
@@ -66,9 +158,253 @@ impl SimpleRandom {
         seed: i32,
         (shelves, slot1, slot2, slot3): (i32, i32, i32, i32),
     ) -> bool {
+        // unknown slots still have to be rolled to keep the next ones in sync
         self.set_seed(seed as i64);
-        self.levels_slot1(shelves) == slot1
-            && self.levels_slot2(shelves) == slot2
-            && self.levels_slot3(shelves) == slot3
+        shows(self.levels_slot1(shelves), slot1)
+            && shows(self.levels_slot2(shelves), slot2)
+            && shows(self.levels_slot3(shelves), slot3)
+    }
+
+    /// Checks up to `batch::MAX_BATCH` seeds against every input at once, bit `i` of the result
+    /// is set if `seeds[i]` matches all of them
+    pub fn verify_seeds(seeds: &[i32], infos: &[(i32, i32, i32, i32)]) -> u32 {
+        batch::verify_seeds(seeds, infos)
+    }
+}
+
+/// The top 4 bits of a state split it into 16 cells, enough for `next_int_bound(16)` which is the
+/// widest roll with 15 bookshelves
+const CELL_BITS: u32 = 44;
+/// Biggest stride tried when looking for one that moves every constrained state slowly
+const MAX_STRIDE: u64 = 1 << 12;
+/// Low 32 bits of a seed get xored with this in `set_seed`
+const SEED_XOR: u64 = MULT as u64 & 0xFFFF_FFFF;
+
+/// Narrows down which XP seeds can match some inputs by only looking at the top bits of the
+/// first six LCG states after `set_seed`, without running `verify_seed` on every seed.
+///
+/// A `next_int_bound` with a power of two bound is just the top bits of the state, so the
+/// `next_int_bound(8)` of each slot and, with 0, 1, 3, 7 or 15 bookshelves, the
+/// `next_int_bound(shelves + 1)` too, tell which sixteenths of their state are possible. Slot 3
+/// doesn't help at 15 bookshelves since it's always 30, most of the information comes from slot 2
+/// there. Any other amount of bookshelves can reject a roll and shift every state after it, so
+/// those inputs only constrain the first state.
+///
+/// Inside of a block of seeds every state is an affine function of the seed, walking the seeds
+/// with a stride that makes the constrained states move slowly lets the walk jump over whole runs
+/// of seeds with the wrong top bits. It's conservative, the seeds it yields still have to be
+/// checked with `verify_seed`
+#[derive(Clone, Debug)]
+pub struct BitConstraints {
+    /// Multiplier, addend and allowed cells of every constrained state
+    states: Vec<(u64, u64, u16)>,
+    /// Seeds get walked in this many interleaved classes
+    stride: u64,
+    /// Some state has no allowed cell, no seed can match
+    impossible: bool,
+}
+
+impl BitConstraints {
+    pub fn new(infos: &[(i32, i32, i32, i32)]) -> Self {
+        let alternatives: Vec<Vec<_>> = infos.iter().map(|&info| vec![info]).collect();
+        Self::with_alternatives(&alternatives)
+    }
+
+    /// Each input is a list of alternatives, a seed has to match at least one of them
+    pub fn with_alternatives(infos: &[Vec<(i32, i32, i32, i32)>]) -> Self {
+        let mut masks = [u16::MAX; 6];
+        for alternatives in infos {
+            let union = alternatives.iter().fold([0; 6], |union, &info| {
+                let masks = state_masks(info);
+                [0, 1, 2, 3, 4, 5].map(|i| union[i] | masks[i])
+            });
+            for (mask, union) in masks.iter_mut().zip(union) {
+                *mask &= union;
+            }
+        }
+
+        // the most selective states go first, each one only gets used if there is a stride
+        // that keeps all of the chosen states slow
+        let mut order: Vec<usize> = (0..6).filter(|&i| masks[i] != u16::MAX).collect();
+        order.sort_by_key(|&i| masks[i].count_ones());
+        let mut chosen: Vec<(u64, u64, u16)> = Vec::new();
+        let mut stride = 1;
+        for i in order {
+            let (mult, addend) = Lcg48::steps(i as u64 + 1);
+            let mut candidate = chosen.clone();
+            candidate.push((mult, addend, masks[i]));
+            if let Some(t) = slow_stride(&candidate) {
+                chosen = candidate;
+                stride = t;
+            }
+        }
+        BitConstraints {
+            states: chosen,
+            stride,
+            impossible: masks.contains(&0),
+        }
+    }
+
+    /// Fraction of the seeds that make it through the constraints that are actually used
+    pub fn selectivity(&self) -> f64 {
+        if self.impossible {
+            return 0.0;
+        }
+        self.states
+            .iter()
+            .map(|&(_, _, mask)| mask.count_ones() as f64 / 16.0)
+            .product()
+    }
+
+    /// Calls `f` with every seed of `range` whose states have allowed top bits, the order
+    /// isn't the seed order
+    pub fn for_each(&self, range: Range<i64>, mut f: impl FnMut(i32)) {
+        if self.impossible {
+            return;
+        }
+        let mut walk: Vec<(u64, u64, u16)> = Vec::with_capacity(self.states.len());
+        for (u, len) in blocks(range) {
+            let w0 = u ^ (SEED_XOR & !(len - 1));
+            // bits 32 to 47 of the state only depend on the sign of the seed
+            let high = ((u as u32 as i32 as i64 ^ MULT) & MASK) as u64 & !0xFFFF_FFFF;
+            for class in 0..self.stride.min(len) {
+                let count = (len - class).div_ceil(self.stride);
+                walk.clear();
+                walk.extend(self.states.iter().map(|&(mult, addend, mask)| {
+                    let state = (high | (w0 + class))
+                        .wrapping_mul(mult)
+                        .wrapping_add(addend);
+                    let delta = mult.wrapping_mul(self.stride);
+                    (state & MASK as u64, delta & MASK as u64, mask)
+                }));
+                let mut j = 0;
+                while j < count {
+                    let mut skip = walk
+                        .iter()
+                        .map(|&(state, delta, mask)| skip(state, delta, mask))
+                        .max()
+                        .unwrap_or(0);
+                    if skip == 0 {
+                        // every state is in an allowed cell, all the seeds until one of them
+                        // leaves its run of allowed cells can go out without checking again
+                        skip = walk
+                            .iter()
+                            .map(|&(state, delta, mask)| run(state, delta, mask))
+                            .min()
+                            .unwrap_or(u64::MAX)
+                            .min(count - j);
+                        for k in j..j + skip {
+                            f(((w0 + class + k * self.stride) ^ SEED_XOR) as u32 as i32);
+                        }
+                    }
+                    j += skip;
+                    for (state, delta, _) in walk.iter_mut() {
+                        *state = state.wrapping_add(skip.wrapping_mul(*delta)) & MASK as u64;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Which sixteenths of each of the first six states can match a single input
+fn state_masks((shelves, slot1, slot2, slot3): (i32, i32, i32, i32)) -> [u16; 6] {
+    let mut masks = [u16::MAX; 6];
+    let exact = shelves & (shelves + 1) == 0;
+    for (slot, level) in [slot1, slot2, slot3].into_iter().enumerate() {
+        let (mut firsts, mut seconds) = (0u16, 0u16);
+        for first in 0..8 {
+            for second in 0..=shelves {
+                let enchantibility = first + 1 + (shelves >> 1) + second;
+                if shows(slot_level(slot as i32, enchantibility, shelves), level) {
+                    firsts |= 0b11 << (first * 2);
+                    if exact {
+                        let width = 16 / (shelves + 1);
+                        seconds |= (((1u32 << width) - 1) << (second * width)) as u16;
+                    }
+                }
+            }
+        }
+        masks[slot * 2] = firsts;
+        if !exact {
+            break;
+        }
+        masks[slot * 2 + 1] = seconds;
+    }
+    masks
+}
+
+/// Smallest stride that moves every state by at most a quarter of a cell, a quarter so that a
+/// run of allowed seeds is at least a few seeds long
+fn slow_stride(states: &[(u64, u64, u16)]) -> Option<u64> {
+    (1..=MAX_STRIDE).find(|&t| {
+        states.iter().all(|&(mult, _, _)| {
+            let delta = mult.wrapping_mul(t) & MASK as u64;
+            cmp::min(delta, (1 << 48) - delta) <= 1 << (CELL_BITS - 2)
+        })
+    })
+}
+
+/// How many strides it takes for `state` to get into an allowed cell, `delta` has to be smaller
+/// than a cell so that no cell can be jumped over
+fn skip(state: u64, delta: u64, mask: u16) -> u64 {
+    let cell = (state >> CELL_BITS) as u32;
+    if mask >> cell & 1 == 1 {
+        return 0;
+    }
+    if delta < 1 << 47 {
+        // going up, to the start of the next allowed cell
+        let next = (cell + 1 + mask.rotate_right(cell + 1).trailing_zeros()) % 16;
+        let distance = ((next as u64) << CELL_BITS).wrapping_sub(state) & MASK as u64;
+        distance.div_ceil(delta)
+    } else {
+        // going down, to the end of the previous allowed cell
+        let previous = (cell + 15 - mask.rotate_left(16 - cell).leading_zeros()) % 16;
+        let end = ((previous as u64 + 1) << CELL_BITS) - 1;
+        let distance = state.wrapping_sub(end) & MASK as u64;
+        distance.div_ceil((1 << 48) - delta)
+    }
+}
+
+/// How many strides `state` stays in allowed cells, it has to be in one already
+fn run(state: u64, delta: u64, mask: u16) -> u64 {
+    let cell = (state >> CELL_BITS) as u32;
+    if mask == u16::MAX {
+        return u64::MAX;
+    }
+    if delta < 1 << 47 {
+        // going up, to the start of the next cell that isn't allowed
+        let next = (cell + 1 + (!mask).rotate_right(cell + 1).trailing_zeros()) % 16;
+        let distance = ((next as u64) << CELL_BITS).wrapping_sub(state) & MASK as u64;
+        distance.div_ceil(delta)
+    } else {
+        // going down, past the start of the lowest allowed cell of the run
+        let previous = (cell + 15 - (!mask).rotate_left(16 - cell).leading_zeros()) % 16;
+        let start = (previous as u64 + 1) << CELL_BITS;
+        let distance = state.wrapping_sub(start) & MASK as u64;
+        distance / ((1 << 48) - delta) + 1
+    }
+}
+
+/// Splits the seeds into aligned power of two blocks of `seed as u32`, xoring such a block with
+/// `SEED_XOR` gives back another block so the low 32 bits of the state stay contiguous
+fn blocks(range: Range<i64>) -> Vec<(u64, u64)> {
+    let mut blocks = Vec::new();
+    for part in [range.start..range.end.min(0), range.start.max(0)..range.end] {
+        let mut u = part.start as u32 as u64;
+        let end = u + (part.end - part.start).max(0) as u64;
+        while u < end {
+            let mut len = if u == 0 {
+                1 << 31
+            } else {
+                1 << u.trailing_zeros().min(31)
+            };
+            while u + len > end {
+                len >>= 1;
+            }
+            blocks.push((u, len));
+            u += len;
+        }
     }
+    blocks
 }
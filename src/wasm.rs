@@ -0,0 +1,584 @@
+//! JS bindings, these are thin wrappers around the Rust API so that it doesn't need to know
+//! about js_sys at all.
+use crate::manipulation::*;
+use crate::{player, progress, search, EnchantmentTableInfo, Error};
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "threads")]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+impl From<Error> for JsValue {
+    fn from(err: Error) -> Self {
+        js_sys::Error::new(&err.to_string()).into()
+    }
+}
+
+#[wasm_bindgen]
+pub struct Cracker {
+    inner: crate::Cracker,
+}
+
+#[wasm_bindgen]
+impl Cracker {
+    #[cfg(not(feature = "parallel"))]
+    #[wasm_bindgen(constructor)]
+    pub fn new(thread_id: usize, threads: usize) -> Self {
+        Cracker {
+            inner: crate::Cracker::new(thread_id, threads),
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Cracker {
+            inner: crate::Cracker::new(),
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    #[wasm_bindgen(getter = possibleSeeds)]
+    pub fn possible_seeds(&self) -> usize {
+        self.inner.possible_seeds()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn seed(&self) -> Result<i32, Error> {
+        self.inner.seed()
+    }
+
+    #[wasm_bindgen(js_name = firstInput)]
+    pub fn first_input(&mut self, info: EnchantmentTableInfo, info2: EnchantmentTableInfo) {
+        self.inner.first_input(info, info2);
+    }
+
+    /// `callback` gets called with the fraction done and the amount of candidates found so far,
+    /// throwing from it cancels the search
+    #[wasm_bindgen(js_name = firstInputWithProgress)]
+    pub fn first_input_with_progress(
+        &mut self,
+        info: EnchantmentTableInfo,
+        info2: EnchantmentTableInfo,
+        callback: &js_sys::Function,
+        cancel: &CancellationToken,
+    ) -> Result<(), Error> {
+        self.inner
+            .first_input_with(info, info2, |p| cancel.report(callback, p), &cancel.inner)
+    }
+
+    pub fn cursor(&self, infos: Vec<EnchantmentTableInfo>) -> Result<SearchCursor, Error> {
+        self.inner
+            .cursor(&infos)
+            .map(|inner| SearchCursor { inner })
+    }
+
+    pub fn resume(&mut self, cursor: &mut SearchCursor, len: u32) {
+        self.inner.resume(&mut cursor.inner, len);
+    }
+
+    /// Same as `firstInputWithProgress` but over every seed left in the cursor
+    #[wasm_bindgen(js_name = resumeWithProgress)]
+    pub fn resume_with_progress(
+        &mut self,
+        cursor: &mut SearchCursor,
+        callback: &js_sys::Function,
+        cancel: &CancellationToken,
+    ) -> Result<(), Error> {
+        self.inner.resume_with(
+            &mut cursor.inner,
+            |p| cancel.report(callback, p),
+            &cancel.inner,
+        )
+    }
+
+    #[wasm_bindgen(js_name = searchChunk)]
+    pub fn search_chunk(
+        &mut self,
+        infos: Vec<EnchantmentTableInfo>,
+        start: i32,
+        len: u32,
+    ) -> Result<(), Error> {
+        self.inner.search_chunk(&infos, start, len)
+    }
+
+    #[wasm_bindgen(js_name = crackAll)]
+    pub fn crack_all(&mut self, infos: Vec<EnchantmentTableInfo>) -> Result<(), Error> {
+        self.inner.crack_all(&infos)
+    }
+
+    /// Same as `firstInputWithProgress` but with every input at once
+    #[wasm_bindgen(js_name = crackAllWithProgress)]
+    pub fn crack_all_with_progress(
+        &mut self,
+        infos: Vec<EnchantmentTableInfo>,
+        callback: &js_sys::Function,
+        cancel: &CancellationToken,
+    ) -> Result<(), Error> {
+        self.inner
+            .crack_all_with(&infos, |p| cancel.report(callback, p), &cancel.inner)
+    }
+
+    #[wasm_bindgen(js_name = addInput)]
+    pub fn add_input(&mut self, info: EnchantmentTableInfo) {
+        self.inner.add_input(info);
+    }
+
+    #[wasm_bindgen(js_name = addClue)]
+    pub fn add_clue(
+        &mut self,
+        item: Item,
+        slot: i32,
+        info: EnchantmentTableInfo,
+        enchantment: Enchantment,
+        level: i32,
+        version: Version,
+    ) -> Result<(), Error> {
+        self.inner
+            .add_clue(item, slot, info, enchantment, level, version)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tolerance(&self) -> usize {
+        self.inner.tolerance()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_tolerance(&mut self, tolerance: usize) {
+        self.inner.set_tolerance(tolerance);
+    }
+
+    pub fn mismatches(&self, seed: i32) -> Vec<u32> {
+        self.inner
+            .mismatches(seed)
+            .into_iter()
+            .map(|x| x as u32)
+            .collect()
+    }
+
+    #[wasm_bindgen(js_name = seedsWithMismatches)]
+    pub fn seeds_with_mismatches(&self, count: usize) -> Vec<i32> {
+        self.inner.seeds_with_mismatches(count)
+    }
+
+    #[wasm_bindgen(getter = observationCount)]
+    pub fn observation_count(&self) -> usize {
+        self.inner.observations().len()
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.inner.undo()
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.inner.redo()
+    }
+
+    #[wasm_bindgen(js_name = removeObservation)]
+    pub fn remove_observation(&mut self, index: usize) -> Result<(), Error> {
+        self.inner.remove_observation(index).map(|_| ())
+    }
+
+    /// Swaps an observation, input or clue, for an input
+    #[wasm_bindgen(js_name = replaceWithInput)]
+    pub fn replace_with_input(
+        &mut self,
+        index: usize,
+        info: EnchantmentTableInfo,
+    ) -> Result<(), Error> {
+        self.inner
+            .replace_observation(index, crate::Observation::Input(info))
+            .map(|_| ())
+    }
+
+    #[wasm_bindgen(js_name = expectedRemaining)]
+    pub fn expected_remaining(&self, shelves: i32) -> Result<f64, Error> {
+        self.inner.expected_remaining(shelves)
+    }
+
+    #[wasm_bindgen(js_name = bestShelves)]
+    pub fn best_shelves(&self) -> Result<i32, Error> {
+        self.inner.best_shelves()
+    }
+
+    pub fn contains(&self, x: i32) -> bool {
+        self.inner.contains(x)
+    }
+
+    /// An `Int32Array` with up to `len` candidates starting from the one at `offset`, from the
+    /// lowest to the highest
+    #[wasm_bindgen(js_name = candidatePage)]
+    pub fn candidate_page(&self, offset: usize, len: usize) -> Vec<i32> {
+        self.inner.candidate_page(offset, len)
+    }
+
+    #[wasm_bindgen(js_name = canMerge)]
+    pub fn can_merge(&self, other: &Cracker) -> bool {
+        self.inner.can_merge(&other.inner)
+    }
+
+    /// `other` is left empty
+    pub fn merge(&mut self, other: &mut Cracker) -> Result<(), Error> {
+        self.inner.merge(&mut other.inner)
+    }
+
+    #[wasm_bindgen(js_name = fromShards)]
+    pub fn from_shards(shards: Vec<Cracker>) -> Result<Cracker, Error> {
+        crate::Cracker::from_shards(shards.into_iter().map(|x| x.inner))
+            .map(|inner| Cracker { inner })
+    }
+
+    #[wasm_bindgen(js_name = exportCandidates)]
+    pub fn export_candidates(&self) -> Vec<u8> {
+        self.inner.export_candidates()
+    }
+
+    #[wasm_bindgen(js_name = exportCandidatesJson)]
+    pub fn export_candidates_json(&self) -> String {
+        self.inner.export_candidates_json()
+    }
+
+    #[wasm_bindgen(js_name = importCandidates)]
+    pub fn import_candidates(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.inner.import_candidates(bytes)
+    }
+
+    #[wasm_bindgen(js_name = importCandidatesJson)]
+    pub fn import_candidates_json(&mut self, json: &str) -> Result<(), Error> {
+        self.inner.import_candidates_json(json)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl Default for Cracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+pub struct SearchCursor {
+    inner: search::SearchCursor,
+}
+
+#[wasm_bindgen]
+impl SearchCursor {
+    #[wasm_bindgen(constructor)]
+    pub fn new(infos: Vec<EnchantmentTableInfo>) -> Result<SearchCursor, Error> {
+        search::SearchCursor::new(&infos).map(|inner| SearchCursor { inner })
+    }
+
+    #[wasm_bindgen(getter = isDone)]
+    pub fn is_done(&self) -> bool {
+        self.inner.is_done()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn progress(&self) -> f64 {
+        self.inner.progress()
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<SearchCursor, Error> {
+        search::SearchCursor::from_bytes(bytes).map(|inner| SearchCursor { inner })
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: progress::CancellationToken,
+}
+
+impl CancellationToken {
+    /// Calls the JS progress callback, cancelling if it throws
+    fn report(&self, callback: &js_sys::Function, p: progress::Progress) {
+        let found = p.found as u32;
+        if callback
+            .call2(&JsValue::NULL, &p.done.into(), &found.into())
+            .is_err()
+        {
+            self.inner.cancel();
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl CancellationToken {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    #[wasm_bindgen(getter = isCancelled)]
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+}
+
+#[wasm_bindgen]
+pub struct Manipulator {
+    inner: crate::Manipulator,
+}
+
+#[wasm_bindgen]
+impl Manipulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed1: u32, seed2: u32) -> Result<Manipulator, Error> {
+        crate::Manipulator::new(seed1, seed2).map(|inner| Manipulator { inner })
+    }
+
+    /// `gaps` has how many times the RNG got used before each XP seed after the first one, 0
+    /// if it isn't known, so it has one less element than `xp_seeds`
+    #[wasm_bindgen(js_name = fromXpSeeds)]
+    pub fn from_xp_seeds(xp_seeds: Vec<u32>, gaps: Vec<u32>) -> Result<Manipulator, Error> {
+        let (&first, rest) = xp_seeds.split_first().ok_or(Error::NoXpSeeds)?;
+        if gaps.len() != rest.len() {
+            return Err(Error::WrongGapCount {
+                seeds: xp_seeds.len(),
+                gaps: gaps.len(),
+            });
+        }
+        let rest: Vec<_> = gaps
+            .into_iter()
+            .map(|x| match x {
+                0 => player::Gap::Unknown,
+                x => player::Gap::Steps(x),
+            })
+            .zip(rest.iter().copied())
+            .collect();
+        crate::Manipulator::from_xp_seeds(first, &rest).map(|inner| Manipulator { inner })
+    }
+
+    #[wasm_bindgen(js_name = changeSeed)]
+    pub fn change_seed(&mut self, seed1: u32, seed2: u32) -> Result<(), Error> {
+        self.inner.change_seed(seed1, seed2)
+    }
+
+    #[wasm_bindgen(getter = playerSeed)]
+    pub fn player_seed(&self) -> js_sys::Uint8Array {
+        let array = js_sys::Uint8Array::new_with_length(6);
+        let bytes = self.inner.player_seed().to_le_bytes();
+        for i in (0..array.length()).rev() {
+            array.set_index(i, bytes[i as usize]);
+        }
+        array
+    }
+
+    #[wasm_bindgen]
+    pub fn simulate(
+        &mut self,
+        item: Item,
+        max_shelves: i32,
+        player_level: i32,
+        version: Version,
+    ) -> Result<SimulationResult, Error> {
+        self.inner
+            .simulate(item, max_shelves, player_level, version)
+            .map(|inner| SimulationResult { inner })
+    }
+
+    #[wasm_bindgen(js_name = simulateWithShelves)]
+    pub fn simulate_with_shelves(
+        &mut self,
+        item: Item,
+        shelves: Vec<i32>,
+        player_level: i32,
+        version: Version,
+    ) -> Result<SimulationResult, Error> {
+        self.inner
+            .simulate_with_shelves(item, &shelves, player_level, version)
+            .map(|inner| SimulationResult { inner })
+    }
+
+    /// `chosen_slot` is one-indexed like the `slot` of a simulation, -1 meaning that nothing was
+    /// enchanted
+    #[wasm_bindgen(js_name = updateSeed)]
+    pub fn update_seed(
+        &mut self,
+        times_needed: i32,
+        chosen_slot: i32,
+        player_level: i32,
+    ) -> Result<i32, Error> {
+        let slot = match chosen_slot {
+            -1 => -1,
+            x => x - 1,
+        };
+        self.inner
+            .update_seed(times_needed, slot, player_level)
+            .map_err(|err| match err {
+                Error::InvalidSlot(_) => Error::InvalidSlot(chosen_slot),
+                err => err,
+            })
+    }
+
+    #[wasm_bindgen(js_name = updateItem)]
+    pub fn update_item(&mut self, item: Item, ench: &EnchantmentInstance) {
+        self.inner.update_item(item, ench);
+    }
+
+    #[wasm_bindgen]
+    pub fn reset(&mut self, item: Item) {
+        self.inner.reset(item);
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SimulationKind {
+    NotFound,
+    Direct,
+    AfterThrows,
+}
+
+#[wasm_bindgen]
+pub struct SimulationResult {
+    inner: crate::SimulationResult,
+}
+
+impl SimulationResult {
+    fn details(&self) -> Option<(i32, i32, &Vec<EnchantmentInstance>, i32)> {
+        match &self.inner {
+            crate::SimulationResult::NotFound => None,
+            crate::SimulationResult::Direct {
+                slot,
+                shelves,
+                enchantments,
+                level,
+            }
+            | crate::SimulationResult::AfterThrows {
+                slot,
+                shelves,
+                enchantments,
+                level,
+                ..
+            } => Some((*slot, *shelves, enchantments, *level)),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl SimulationResult {
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> SimulationKind {
+        match self.inner {
+            crate::SimulationResult::NotFound => SimulationKind::NotFound,
+            crate::SimulationResult::Direct { .. } => SimulationKind::Direct,
+            crate::SimulationResult::AfterThrows { .. } => SimulationKind::AfterThrows,
+        }
+    }
+
+    #[wasm_bindgen(getter = timesNeeded)]
+    pub fn times_needed(&self) -> i32 {
+        self.inner.times_needed()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn throws(&self) -> Option<i32> {
+        match self.inner {
+            crate::SimulationResult::AfterThrows { throws, .. } => Some(throws),
+            _ => None,
+        }
+    }
+
+    /// One-indexed, same as what `updateSeed` takes
+    #[wasm_bindgen(getter)]
+    pub fn slot(&self) -> Option<i32> {
+        self.details().map(|(slot, ..)| slot + 1)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn shelves(&self) -> Option<i32> {
+        self.details().map(|(_, shelves, ..)| shelves)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn level(&self) -> Option<i32> {
+        self.details().map(|(.., level)| level)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn enchantments(&self) -> Vec<EnchantmentInstance> {
+        self.details()
+            .map(|(_, _, enchantments, _)| enchantments.clone())
+            .unwrap_or_default()
+    }
+}
+
+// this is is an struct because yay js util class! (not very rusty, ikr)
+// I do all this because you cant put methods into enums shared to js
+#[wasm_bindgen]
+pub struct Utilities;
+
+#[wasm_bindgen]
+impl Utilities {
+    fn get_introduced_version(thing: &dyn Introduced) -> Version {
+        //Traits are not supported yet for wasm
+        thing.get_introduced_version()
+    }
+
+    #[wasm_bindgen(js_name = materialIntroducedVersion)]
+    pub fn material_introduced_version(mat: Material) -> Version {
+        Self::get_introduced_version(&mat)
+    }
+
+    #[wasm_bindgen(js_name = itemIntroducedVersion)]
+    pub fn item_introduced_version(item: Item) -> Version {
+        Self::get_introduced_version(&item)
+    }
+
+    #[wasm_bindgen(js_name = enchantmentIntroducedVersion)]
+    pub fn enchantment_introduced_version(ench: Enchantment) -> Version {
+        Self::get_introduced_version(&ench)
+    }
+
+    #[wasm_bindgen(js_name = getMaxLevelInTable)]
+    pub fn get_max_level_in_table(ench: Enchantment, item: Item) -> i32 {
+        ench.get_max_level_in_table(item)
+    }
+
+    #[wasm_bindgen(js_name = areEnchantmentsCompatible)]
+    pub fn are_enchantments_compatible(
+        ench1: Enchantment,
+        ench2: Enchantment,
+        version: Version,
+    ) -> bool {
+        ench1.is_compatible_with(ench2, version)
+    }
+
+    #[wasm_bindgen(js_name = getEnchantments)]
+    pub fn get_enchantments(item: Item) -> js_sys::Uint8Array {
+        let enchs = item.get_enchantments();
+        let arr = js_sys::Uint8Array::new_with_length(enchs.len() as u32);
+        for (i, ench) in enchs.into_iter().enumerate() {
+            arr.set_index(i as u32, ench as u8);
+        }
+        arr
+    }
+
+    #[wasm_bindgen(js_name = isTreasure)]
+    pub fn is_treasure(ench: Enchantment) -> bool {
+        ench.is_treasure()
+    }
+
+    #[wasm_bindgen(js_name = getItems)]
+    pub fn get_items(material: Material) -> js_sys::Uint8Array {
+        let arr = js_sys::Uint8Array::new_with_length(SET_MATERIAL as u32);
+        for (i, item) in material.get_items().iter().enumerate() {
+            arr.set_index(i as u32, *item as u8);
+        }
+        arr
+    }
+}
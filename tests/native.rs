@@ -0,0 +1,1190 @@
+//! Test suite for the Rust API, without any of the wasm glue.
+extern crate libenchcrack;
+use libenchcrack::manipulation::*;
+use libenchcrack::progress::*;
+use libenchcrack::random::JavaRandom;
+use libenchcrack::*;
+
+#[test]
+fn manipulator() {
+    let item = Item::NetheriteSword;
+    let mut man = Manipulator::new(2893231007, 2635886329).expect("Wrong seeds");
+    assert_eq!(man.player_seed(), 0x9d1c_6ef9_3d2e);
+    let enchs = [
+        EnchantmentInstance::new(Enchantment::BaneOfArthropods, 5),
+        EnchantmentInstance::new(Enchantment::FireAspect, 2),
+        EnchantmentInstance::new(Enchantment::Knockback, -1),
+    ];
+    for ench in enchs.iter() {
+        man.update_item(item, ench);
+    }
+    match man.simulate(item, 15, 999, Version::V1_16) {
+        Ok(SimulationResult::AfterThrows {
+            throws,
+            slot,
+            shelves,
+            enchantments,
+            level,
+        }) => {
+            assert_eq!((throws, slot, shelves, level), (57, 2, 15, 30));
+            assert!(
+                enchantments.contains(&EnchantmentInstance::new(Enchantment::BaneOfArthropods, 5))
+            );
+            assert!(enchantments.contains(&EnchantmentInstance::new(Enchantment::FireAspect, 2)));
+            // the slot of the result goes straight into update_seed
+            assert_eq!(man.update_seed(throws, slot, 999), Ok(995));
+        }
+        x => panic!("Unexpected simulation {:?}", x),
+    }
+}
+
+#[test]
+fn fixed_shelves() {
+    let item = Item::NetheriteSword;
+    let mut man = Manipulator::new(2893231007, 2635886329).expect("Wrong seeds");
+    man.update_item(
+        item,
+        &EnchantmentInstance::new(Enchantment::BaneOfArthropods, 5),
+    );
+    let full = man.simulate(item, 15, 999, Version::V1_16);
+    assert_eq!(
+        man.simulate_with_shelves(item, &(0..=15).collect::<Vec<_>>(), 999, Version::V1_16),
+        full
+    );
+    for shelves in [vec![15], vec![0, 15], vec![15, 0, 15]] {
+        match man.simulate_with_shelves(item, &shelves, 999, Version::V1_16) {
+            Ok(SimulationResult::AfterThrows {
+                throws,
+                shelves: found,
+                ..
+            }) => {
+                assert!(shelves.contains(&found));
+                // fewer options can only push the plan further away
+                if let Ok(SimulationResult::AfterThrows { throws: best, .. }) = full {
+                    assert!(throws >= best);
+                }
+            }
+            x => panic!("Unexpected simulation {:?}", x),
+        }
+    }
+    // without bookshelves the table never rolls bane of arthropods 5
+    assert_eq!(
+        man.simulate_with_shelves(item, &[0], 999, Version::V1_16),
+        Ok(SimulationResult::NotFound)
+    );
+    assert_eq!(
+        man.simulate_with_shelves(item, &[15, 0], 999, Version::V1_16),
+        man.simulate_with_shelves(item, &[0, 15, 0], 999, Version::V1_16)
+    );
+    assert_eq!(
+        man.simulate_with_shelves(item, &[0, 16], 999, Version::V1_16),
+        Err(Error::InvalidShelves(16))
+    );
+    assert_eq!(
+        man.simulate_with_shelves(item, &[], 999, Version::V1_16),
+        Err(Error::NoShelves)
+    );
+}
+
+#[test]
+fn player_seed_recovery() {
+    let step = |state: u64, n: u32| {
+        (0..n).fold(state, |x, _| {
+            x.wrapping_mul(0x5DEECE66D).wrapping_add(0xB) & 0xFFFF_FFFF_FFFF
+        })
+    };
+    let xp_seed = |state: u64| (state >> 16) as u32;
+    // an enchantment, 3 throws and then 200 throws with a dummy enchantment
+    let mut states = vec![0x9d1c_6ef9_3d2e];
+    for steps in [1, 1 + 4 * 3, 1 + 4 * 200 + 1] {
+        states.push(step(*states.last().unwrap(), steps));
+    }
+
+    let first = xp_seed(states[0]);
+    assert!(
+        player::player_seeds(first, &[(player::Gap::Steps(1), xp_seed(states[1]))])
+            .contains(&states[1])
+    );
+    assert_eq!(
+        player::player_seeds(
+            first,
+            &[(player::Gap::after_throws(3), xp_seed(step(states[0], 13)))]
+        ),
+        vec![step(states[0], 13)]
+    );
+    let rest = [
+        (player::Gap::Steps(1), xp_seed(states[1])),
+        (player::Gap::after_throws(3), xp_seed(states[2])),
+        (player::Gap::Unknown, xp_seed(states[3])),
+    ];
+    let man = Manipulator::from_xp_seeds(first, &rest).unwrap();
+    assert_eq!(man.player_seed(), states[3]);
+
+    assert_eq!(
+        Manipulator::calculate_seeds(first, xp_seed(states[1])),
+        vec![states[1]]
+    );
+
+    // xp seed 41436512 comes up twice in the 8194 steps after this state
+    let state = step(0x3ffb_f7f4_ebad, 1);
+    let ambiguous = [
+        (player::Gap::Steps(1), xp_seed(state)),
+        (player::Gap::Unknown, 41436512),
+    ];
+    assert_eq!(
+        Manipulator::from_xp_seeds(xp_seed(0x3ffb_f7f4_ebad), &ambiguous).err(),
+        Some(Error::AmbiguousSeed(2))
+    );
+    let resolved = [
+        ambiguous[0],
+        ambiguous[1],
+        (player::Gap::Steps(1), xp_seed(step(state, 2624))),
+    ];
+    assert_eq!(
+        Manipulator::from_xp_seeds(xp_seed(0x3ffb_f7f4_ebad), &resolved)
+            .unwrap()
+            .player_seed(),
+        step(state, 2624)
+    );
+
+    assert_eq!(
+        Manipulator::from_xp_seeds(first, &[]).err(),
+        Some(Error::AmbiguousSeed(1 << 16))
+    );
+    assert_eq!(
+        Manipulator::from_xp_seeds(first, &[(player::Gap::Steps(1), 12345)]).err(),
+        Some(Error::NoSeedFound)
+    );
+    // no XP seeds still need no gaps
+    assert_eq!(
+        Error::WrongGapCount { seeds: 0, gaps: 1 }.to_string(),
+        "0 XP seeds need 0 gaps but 1 were given"
+    );
+}
+
+#[test]
+fn lcg_jumps() {
+    let start = utils::Lcg48::new(0x9d1c_6ef9_3d2e);
+    let mut stepped = start;
+    for n in 1..=300u64 {
+        stepped.step();
+        let mut jumped = start;
+        jumped.skip(n);
+        assert_eq!(jumped, stepped, "{}", n);
+        jumped.rewind(n);
+        assert_eq!(jumped, start, "{}", n);
+    }
+
+    let mut rng = start;
+    rng.skip(1 << 48);
+    assert_eq!(rng, start);
+    rng.rewind(u64::MAX);
+    rng.skip(u64::MAX % (1 << 48));
+    assert_eq!(rng, start);
+    let mut java = java_rand::Random::new(0);
+    java.set_seed(0x5DEECE66D ^ start.state());
+    // java's nextInt is the top 32 bits of the next state
+    let mut rng = start;
+    rng.skip(8195);
+    for _ in 0..8194 {
+        java.next_i32();
+    }
+    assert_eq!(java.next_i32(), (rng.state() >> 16) as i32);
+}
+
+#[test]
+fn java_random_matches_reference() {
+    for seed in [0, 1, -500, 150123, i64::MIN, i64::MAX, 0x5DEECE66D] {
+        let mut rng = utils::SimpleRandom::new(seed);
+        let mut java = java_rand::Random::new(seed as u64);
+        for bound in 1..200 {
+            assert_eq!(rng.next_int(), java.next_i32());
+            assert_eq!(rng.next_int_bound(bound), java.next_i32_bound(bound));
+            assert_eq!(
+                rng.next_int_bound(i32::MAX - bound),
+                java.next_i32_bound(i32::MAX - bound)
+            );
+            assert_eq!(rng.next_long(), java.next_i64());
+            assert_eq!(rng.next_boolean(), java.next_bool());
+            assert_eq!(rng.next_float(), java.next_f32());
+            assert_eq!(rng.next_double(), java.next_f64());
+            assert_eq!(rng.next_gaussian(), java.next_gaussian());
+        }
+    }
+}
+
+/// Counts the bits asked for, to check how much of the RNG each roll uses
+#[derive(Default)]
+struct Recording {
+    inner: utils::SimpleRandom,
+    calls: Vec<u32>,
+}
+
+impl JavaRandom for Recording {
+    fn set_seed(&mut self, seed: i64) {
+        self.inner.set_seed(seed);
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.calls.push(bits);
+        self.inner.next(bits)
+    }
+
+    fn next_next_gaussian(&mut self) -> &mut Option<f64> {
+        self.inner.next_next_gaussian()
+    }
+}
+
+#[test]
+fn recording_random() {
+    let mut rand: Recording = Default::default();
+    rand.set_seed(-329083225);
+    let level = Enchantment::calc_enchantment_table_level(&mut rand, 2, 15, Item::Book).unwrap();
+    assert_eq!(level, 30);
+    // both rolls have power of two bounds, so nothing gets rolled again
+    assert_eq!(rand.calls, vec![31, 31]);
+
+    rand.calls.clear();
+    let enchantments =
+        Enchantment::get_enchantments_in_table(&mut rand, 0, Item::Book, 0, 5, Version::V1_16);
+    assert!(!enchantments.is_empty());
+    // the two enchantability rolls and the two floats go first
+    assert_eq!(rand.calls[..4], [31, 31, 24, 24]);
+}
+
+#[test]
+fn item_enchantments() {
+    let enchs = Item::Bow.get_enchantments();
+    assert_eq!(enchs.len(), 5);
+    assert!(enchs.contains(&Enchantment::Infinity));
+    assert!(!enchs.contains(&Enchantment::Mending));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(Manipulator::new(0, 12345).err(), Some(Error::NoSeedFound));
+    let mut man = Manipulator::new(2893231007, 2635886329).expect("Wrong seeds");
+    assert_eq!(
+        man.simulate(Item::Bow, 15, 30, Version::V1_16),
+        Err(Error::NoWantedEnchantments(Item::Bow))
+    );
+    assert_eq!(
+        man.simulate(Item::Trident, 15, 30, Version::V1_8),
+        Err(Error::UnsupportedVersion(Item::Trident, Version::V1_8))
+    );
+    assert_eq!(
+        man.simulate(Item::Bow, 16, 30, Version::V1_16),
+        Err(Error::InvalidShelves(16))
+    );
+    assert_eq!(man.update_seed(-1, 3, 30), Err(Error::InvalidSlot(3)));
+    let mut rand = utils::SimpleRandom::new(0);
+    assert_eq!(
+        Enchantment::calc_enchantment_table_level(&mut rand, 3, 15, Item::Bow),
+        Err(Error::InvalidSlot(3))
+    );
+}
+
+#[test]
+fn table_info_validation() {
+    assert!(EnchantmentTableInfo::new(15, 5, 20, 30).is_ok());
+    assert!(EnchantmentTableInfo::new(4, 1, 9, 8).is_ok());
+    assert_eq!(
+        EnchantmentTableInfo::new(40, 5, 20, 30).err(),
+        Some(Error::InvalidShelves(40))
+    );
+    assert_eq!(
+        EnchantmentTableInfo::new(15, 31, 20, 30).err(),
+        Some(Error::ImpossibleLevel {
+            shelves: 15,
+            slot: 0,
+            level: 31
+        })
+    );
+    assert_eq!(
+        EnchantmentTableInfo::new(15, 5, 20, 29).err(),
+        Some(Error::ImpossibleLevel {
+            shelves: 15,
+            slot: 2,
+            level: 29
+        })
+    );
+
+    // every level the game can roll has to be accepted
+    let mut rand = utils::SimpleRandom::new(0);
+    for seed in 0..200 {
+        for shelves in 0..=15 {
+            rand.set_seed(seed);
+            let mut levels = [0; 3];
+            for (slot, level) in levels.iter_mut().enumerate() {
+                *level = Enchantment::calc_enchantment_table_level(
+                    &mut rand,
+                    slot as i32,
+                    shelves,
+                    Item::Book,
+                )
+                .unwrap();
+            }
+            assert!(EnchantmentTableInfo::new(shelves, levels[0], levels[1], levels[2]).is_ok());
+        }
+    }
+}
+
+#[test]
+fn unknown_shelves() {
+    // slot 3 can only cost 30 levels with 15 bookshelves
+    let info = EnchantmentTableInfo::unknown_shelves(5, 20, 30).unwrap();
+    assert_eq!(info.possible_shelves(), vec![15]);
+    assert_eq!(info.shelves(), Some(15));
+    let info2 = EnchantmentTableInfo::with_shelf_range(10, 13, 5, 10, 24).unwrap();
+    assert_eq!(info2.shelves(), None);
+    let shelves = info2.possible_shelves();
+    assert!(shelves.contains(&12) && shelves.len() > 1);
+    assert!(shelves.windows(2).all(|x| x[0] < x[1]));
+    assert!(info2.alternatives().map(|x| x.0).eq(shelves));
+    assert_eq!(
+        EnchantmentTableInfo::with_shelf_range(0, 16, 5, 10, 24).err(),
+        Some(Error::InvalidShelves(16))
+    );
+    assert_eq!(
+        EnchantmentTableInfo::with_shelf_range(13, 10, 5, 10, 24).err(),
+        Some(Error::InvalidShelfRange { min: 13, max: 10 })
+    );
+    assert_eq!(
+        EnchantmentTableInfo::with_shelf_range(0, 3, 5, 20, 30).err(),
+        Some(Error::InconsistentLevels)
+    );
+
+    let start = KNOWN_SEED - 2_000_000;
+    let whole = cracker_near_known_seed();
+    let mut ranged = full_cracker();
+    ranged
+        .search_chunk(&[info, info2], start, 4_000_000)
+        .unwrap();
+    let seeds = candidates::decode(&ranged.export_candidates()).unwrap();
+    assert!(ranged.contains(KNOWN_SEED));
+    assert!(candidates::decode(&whole.export_candidates())
+        .unwrap()
+        .iter()
+        .all(|x| seeds.contains(x)));
+    let mut rng = utils::SimpleRandom::default();
+    for seed in start..start + 4_000_000 {
+        assert_eq!(
+            seeds.binary_search(&seed).is_ok(),
+            info.matches(&mut rng, seed) && info2.matches(&mut rng, seed),
+            "{}",
+            seed
+        );
+    }
+
+    let cursor = search::SearchCursor::new(&[info, info2]).unwrap();
+    let bytes = cursor.to_bytes();
+    assert_eq!(search::SearchCursor::from_bytes(&bytes).unwrap(), cursor);
+}
+
+#[test]
+fn partial_observations() {
+    assert_eq!(
+        EnchantmentTableInfo::partial(12, None, Some(10), Some(24)),
+        EnchantmentTableInfo::new(12, 0, 10, 24)
+    );
+    assert_eq!(
+        EnchantmentTableInfo::partial(15, None, Some(31), None).err(),
+        Some(Error::ImpossibleLevel {
+            shelves: 15,
+            slot: 1,
+            level: 31
+        })
+    );
+    assert_eq!(
+        EnchantmentTableInfo::unknown_shelves(0, 0, 30)
+            .unwrap()
+            .possible_shelves(),
+        vec![15]
+    );
+    // would match every seed
+    assert_eq!(
+        EnchantmentTableInfo::partial(15, None, None, None).err(),
+        Some(Error::NoKnownLevels)
+    );
+    assert_eq!(
+        EnchantmentTableInfo::unknown_shelves(0, 0, 0).err(),
+        Some(Error::NoKnownLevels)
+    );
+
+    // the hidden slot still has to go through the rejection loop
+    let mut rng: utils::SimpleRandom = Default::default();
+    assert!(rng.verify_seed(41066301, (12, 0, 13, 24)));
+    assert!(rng.verify_seed(41066301, (12, 4, 0, 24)));
+    assert!(!rng.verify_seed(41066301, (12, 5, 0, 24)));
+
+    let info = EnchantmentTableInfo::partial(15, Some(5), Some(20), None).unwrap();
+    let info2 = EnchantmentTableInfo::partial(12, None, Some(10), Some(24)).unwrap();
+    let mut cracker = full_cracker();
+    cracker
+        .search_chunk(&[info, info2], -329083225 - 500_000, 1_000_000)
+        .unwrap();
+    assert!(cracker.contains(-329083225));
+    cracker.add_input(EnchantmentTableInfo::partial(8, Some(2), None, None).unwrap());
+    assert!(cracker.contains(-329083225));
+}
+
+#[test]
+fn enchantment_clues() {
+    let seed = -329083225;
+    let info = EnchantmentTableInfo::new(15, 5, 20, 30).unwrap();
+    let mut cracker = full_cracker();
+    cracker
+        .search_chunk(&[info], seed - 200_000, 400_000)
+        .unwrap();
+    let before = cracker.possible_seeds();
+
+    let mut rand = utils::SimpleRandom::new(seed as i64);
+    for slot in 0..2 {
+        Enchantment::calc_enchantment_table_level(&mut rand, slot, 15, Item::Book).unwrap();
+    }
+    let clue =
+        Enchantment::get_enchantment_clue(&mut rand, seed, Item::Book, 2, 30, Version::V1_16)
+            .unwrap();
+    cracker
+        .add_clue(
+            Item::Book,
+            2,
+            info,
+            clue.enchantment,
+            clue.level,
+            Version::V1_16,
+        )
+        .unwrap();
+    assert!(cracker.contains(seed));
+    assert!(cracker.possible_seeds() * 4 < before);
+
+    assert_eq!(
+        cracker.add_clue(Item::Book, 3, info, clue.enchantment, 1, Version::V1_16),
+        Err(Error::InvalidSlot(3))
+    );
+    assert_eq!(
+        cracker.add_clue(
+            Item::Crossbow,
+            0,
+            info,
+            Enchantment::Multishot,
+            1,
+            Version::V1_8
+        ),
+        Err(Error::UnsupportedVersion(Item::Crossbow, Version::V1_8))
+    );
+    // a clue the slot can't show leaves nothing
+    cracker
+        .add_clue(Item::Book, 0, info, Enchantment::Mending, 1, Version::V1_16)
+        .unwrap();
+    assert_eq!(cracker.possible_seeds(), 0);
+}
+
+#[test]
+fn tolerant_inputs() {
+    let seed = KNOWN_SEED;
+    let mut cracker = cracker_near_known_seed();
+    cracker.set_tolerance(1);
+    let inputs = [
+        (8, 2, 10, 16),
+        (6, 3, 9, 13),
+        (4, 1, 9, 8),
+        (3, 1, 9, 6),
+        (7, 2, 13, 14),
+    ];
+    for (shelves, slot1, slot2, slot3) in inputs {
+        cracker.add_input(EnchantmentTableInfo::new(shelves, slot1, slot2, slot3).unwrap());
+    }
+    // the second one was mistyped, slot 3 was 12
+    assert!(cracker.contains(seed));
+    assert_eq!(cracker.mismatches(seed), vec![1]);
+    assert_eq!(cracker.seeds_with_mismatches(1), vec![seed]);
+    assert!(cracker.seeds_with_mismatches(0).is_empty());
+    assert_eq!(cracker.seed(), Ok(seed));
+
+    cracker.set_tolerance(0);
+    assert_eq!(cracker.possible_seeds(), 0);
+    cracker.reset();
+    assert!(cracker.mismatches(seed).is_empty());
+}
+
+#[test]
+fn observation_history() {
+    let seed = KNOWN_SEED;
+    let mut cracker = cracker_near_known_seed();
+    let found = cracker.possible_seeds();
+    let typo = EnchantmentTableInfo::new(6, 3, 9, 13).unwrap();
+    cracker.add_input(EnchantmentTableInfo::new(8, 2, 10, 16).unwrap());
+    cracker.add_input(typo);
+    let after_typo = cracker.possible_seeds();
+    assert!(!cracker.contains(seed));
+
+    assert!(cracker.undo());
+    assert!(cracker.contains(seed));
+    assert!(cracker.redo());
+    assert!(!cracker.redo());
+    assert_eq!(cracker.possible_seeds(), after_typo);
+    assert_eq!(cracker.observations()[1], Observation::Input(typo));
+
+    let fixed = Observation::Input(EnchantmentTableInfo::new(6, 3, 9, 12).unwrap());
+    assert_eq!(
+        cracker.replace_observation(1, fixed.clone()),
+        Ok(Observation::Input(typo))
+    );
+    assert!(cracker.contains(seed));
+    assert_eq!(cracker.remove_observation(2), Err(Error::NoObservation(2)));
+    assert!(cracker.remove_observation(0).is_ok());
+    assert_eq!(cracker.observations(), &[fixed]);
+    assert!(cracker.undo());
+    assert!(!cracker.undo());
+    assert_eq!(cracker.possible_seeds(), found);
+
+    // mismatches follow the observations when the history changes
+    cracker.set_tolerance(1);
+    cracker.add_input(typo);
+    cracker.add_input(EnchantmentTableInfo::new(4, 1, 9, 8).unwrap());
+    assert_eq!(cracker.mismatches(seed), vec![0]);
+    cracker.remove_observation(0).unwrap();
+    assert!(cracker.mismatches(seed).is_empty());
+}
+
+#[test]
+fn search_after_observations() {
+    let (seed, infos) = (KNOWN_SEED, known_inputs());
+    let typo = EnchantmentTableInfo::new(6, 3, 9, 13).unwrap();
+    let input = EnchantmentTableInfo::new(8, 2, 10, 16).unwrap();
+    for tolerance in [0, 1] {
+        let mut whole = cracker_near_known_seed();
+        whole.set_tolerance(tolerance);
+        whole.add_input(typo);
+        whole.add_input(input);
+
+        let mut cracker = full_cracker();
+        cracker.set_tolerance(tolerance);
+        cracker
+            .search_chunk(&infos, seed - 2_000_000, 2_000_000)
+            .unwrap();
+        cracker.add_input(typo);
+        cracker.add_input(input);
+        cracker.search_chunk(&infos, seed, 2_000_000).unwrap();
+        let found: Vec<i32> = cracker.candidates().collect();
+        assert_eq!(found, whole.candidates().collect::<Vec<_>>());
+        assert_eq!(
+            cracker.seeds_with_mismatches(1),
+            whole.seeds_with_mismatches(1)
+        );
+        assert_eq!(cracker.contains(seed), tolerance == 1);
+
+        cracker.undo();
+        cracker.redo();
+        assert_eq!(cracker.candidates().collect::<Vec<_>>(), found);
+    }
+}
+
+#[test]
+fn expected_remaining() {
+    let mut cracker = full_cracker();
+    assert_eq!(
+        cracker.expected_remaining(15),
+        Err(Error::EmptyCandidateSet)
+    );
+    cracker = cracker_near_known_seed();
+    let found = cracker.possible_seeds() as f64;
+    assert_eq!(
+        cracker.expected_remaining(16),
+        Err(Error::InvalidShelves(16))
+    );
+    // the same amount of bookshelves shows the same levels again
+    assert_eq!(cracker.expected_remaining(15), Ok(found));
+    let best = cracker.best_shelves().unwrap();
+    let expected = cracker.expected_remaining(best).unwrap();
+    assert!(expected < found / 4.0);
+    for shelves in 0..=15 {
+        assert!(cracker.expected_remaining(shelves).unwrap() >= expected);
+    }
+
+    // it's exact with few candidates, check it against the real groups
+    let mut rng: utils::SimpleRandom = Default::default();
+    let seeds = candidates::decode(&cracker.export_candidates()).unwrap();
+    let total: usize = seeds
+        .iter()
+        .map(|&x| {
+            let levels = rng.levels(x, best);
+            seeds
+                .iter()
+                .filter(|&&y| rng.levels(y, best) == levels)
+                .count()
+        })
+        .sum();
+    let exact = total as f64 / found;
+    assert!((exact - expected).abs() < 1e-9);
+}
+
+#[cfg(not(feature = "parallel"))]
+#[test]
+fn first_input_progress() {
+    let info = EnchantmentTableInfo::new(15, 5, 20, 30).unwrap();
+    let info2 = EnchantmentTableInfo::new(12, 5, 10, 24).unwrap();
+
+    // 2^24 seeds, a few chunks
+    let mut cracker = Cracker::new(0, 256);
+    let mut reports: Vec<Progress> = Vec::new();
+    cracker
+        .first_input_with(info, info2, |p| reports.push(p), &CancellationToken::new())
+        .unwrap();
+    assert_eq!(reports.len(), (1 << 24) / PROGRESS_CHUNK as usize);
+    assert!(reports
+        .windows(2)
+        .all(|x| x[0].done < x[1].done && x[0].found <= x[1].found));
+    let last = reports.last().unwrap();
+    assert_eq!(last.done, 1.0);
+    assert_eq!(last.found, cracker.possible_seeds());
+}
+
+#[test]
+fn resume_progress() {
+    // a few chunks around the seed from the web tests, with or without the parallel feature
+    let start = KNOWN_SEED as i64 - 2 * PROGRESS_CHUNK as i64;
+    let range = start..start + 4 * PROGRESS_CHUNK as i64;
+    let mut cracker = full_cracker();
+    let mut cursor = search::SearchCursor::with_range(&known_inputs(), range.clone()).unwrap();
+    let mut reports: Vec<Progress> = Vec::new();
+    cracker
+        .resume_with(&mut cursor, |p| reports.push(p), &CancellationToken::new())
+        .unwrap();
+    assert_eq!(reports.len(), 4);
+    assert!(reports
+        .windows(2)
+        .all(|x| x[0].done < x[1].done && x[0].found <= x[1].found));
+    let last = reports.last().unwrap();
+    assert_eq!(last.done, 1.0);
+    assert_eq!(last.found, cracker.possible_seeds());
+    assert!(cursor.is_done());
+    assert!(cracker.contains(KNOWN_SEED));
+
+    // cancelling leaves both the cracker and the cursor where they were
+    let found = cracker.possible_seeds();
+    let mut cursor = search::SearchCursor::with_range(&known_inputs(), range.clone()).unwrap();
+    let cancel = CancellationToken::new();
+    let result = cracker.resume_with(&mut cursor, |_| cancel.cancel(), &cancel);
+    assert_eq!(result, Err(Error::Cancelled));
+    assert_eq!(cursor.remaining(), range);
+    assert_eq!(cracker.possible_seeds(), found);
+}
+
+#[cfg(not(feature = "parallel"))]
+fn full_cracker() -> Cracker {
+    Cracker::new(0, 1)
+}
+
+#[cfg(feature = "parallel")]
+fn full_cracker() -> Cracker {
+    Cracker::new()
+}
+
+/// The seed from the web tests, `known_inputs` are the first two inputs it shows
+const KNOWN_SEED: i32 = -329083225;
+
+fn known_inputs() -> [EnchantmentTableInfo; 2] {
+    [
+        EnchantmentTableInfo::new(15, 5, 20, 30).unwrap(),
+        EnchantmentTableInfo::new(12, 5, 10, 24).unwrap(),
+    ]
+}
+
+/// Every seed within 2 million of `KNOWN_SEED` that matches `known_inputs`
+fn cracker_near_known_seed() -> Cracker {
+    let mut cracker = full_cracker();
+    cracker
+        .search_chunk(&known_inputs(), KNOWN_SEED - 2_000_000, 4_000_000)
+        .unwrap();
+    cracker
+}
+
+#[test]
+fn first_input_cancel() {
+    let info = EnchantmentTableInfo::new(15, 5, 20, 30).unwrap();
+    let info2 = EnchantmentTableInfo::new(12, 5, 10, 24).unwrap();
+
+    let mut cracker = full_cracker();
+    let cancel = CancellationToken::new();
+    let mut calls = 0;
+    let result = cracker.first_input_with(
+        info,
+        info2,
+        |_| {
+            calls += 1;
+            cancel.cancel();
+        },
+        &cancel,
+    );
+    assert_eq!(result, Err(Error::Cancelled));
+    assert_eq!(calls, 1);
+    assert_eq!(cracker.possible_seeds(), 0);
+}
+
+#[cfg(not(feature = "parallel"))]
+#[test]
+fn first_input_cancel_keeps_candidates() {
+    let info = EnchantmentTableInfo::new(15, 5, 20, 30).unwrap();
+    let info2 = EnchantmentTableInfo::new(12, 5, 10, 24).unwrap();
+
+    // two chunks, one of them with the seed from the web tests
+    let mut cracker = Cracker::new(216, 512);
+    cracker.first_input(info, info2);
+    assert!(cracker.contains(-329083225));
+    cracker.add_input(EnchantmentTableInfo::new(8, 2, 10, 16).unwrap());
+    let found = cracker.possible_seeds();
+    let cancel = CancellationToken::new();
+    let result = cracker.first_input_with(info, info2, |_| cancel.cancel(), &cancel);
+    assert_eq!(result, Err(Error::Cancelled));
+    assert_eq!(cracker.possible_seeds(), found);
+    assert_eq!(cracker.observations().len(), 1);
+}
+
+#[cfg(not(feature = "parallel"))]
+#[test]
+fn shards_cover_every_seed() {
+    let info = EnchantmentTableInfo::new(15, 5, 20, 30).unwrap();
+    for threads in [1, 3, 7, 16] {
+        let mut end = search::SEED_SPACE.start;
+        for id in 0..threads {
+            let range = Cracker::new(id, threads)
+                .cursor(&[info])
+                .unwrap()
+                .remaining();
+            assert_eq!(range.start, end);
+            end = range.end;
+        }
+        assert_eq!(end, search::SEED_SPACE.end);
+    }
+}
+
+#[test]
+fn chunked_search() {
+    let info = EnchantmentTableInfo::new(15, 5, 20, 30).unwrap();
+    let info2 = EnchantmentTableInfo::new(12, 5, 10, 24).unwrap();
+    let range = -3_000_000i64..1_000_000;
+
+    let mut whole = full_cracker();
+    whole
+        .search_chunk(&[info, info2], range.start as i32, 4_000_000)
+        .unwrap();
+
+    let mut chunked = full_cracker();
+    let mut cursor = search::SearchCursor::with_range(&[info, info2], range).unwrap();
+    chunked.resume(&mut cursor, 1_500_000);
+    assert!(!cursor.is_done());
+    // pause and pick it back up from the bytes
+    let mut cursor = search::SearchCursor::from_bytes(&cursor.to_bytes()).unwrap();
+    while !cursor.is_done() {
+        chunked.resume(&mut cursor, 1_000_000);
+    }
+    assert_eq!(cursor.progress(), 1.0);
+    assert_eq!(chunked.possible_seeds(), whole.possible_seeds());
+    assert!(whole.possible_seeds() > 0);
+
+    // the amount of inputs doesn't fit in a byte
+    let cursor = search::SearchCursor::new(&[info; 300]).unwrap();
+    assert_eq!(
+        search::SearchCursor::from_bytes(&cursor.to_bytes()).unwrap(),
+        cursor
+    );
+    assert_eq!(
+        search::SearchCursor::from_bytes(&[0; 5]),
+        Err(Error::InvalidCursor)
+    );
+
+    // a cursor without inputs would keep every seed
+    assert_eq!(search::SearchCursor::new(&[]), Err(Error::NoInputs));
+    assert_eq!(
+        search::SearchCursor::with_range(&[], 0..10),
+        Err(Error::NoInputs)
+    );
+    assert_eq!(whole.cursor(&[]).err(), Some(Error::NoInputs));
+    assert_eq!(whole.search_chunk(&[], 0, 10), Err(Error::NoInputs));
+    assert_eq!(
+        search::SearchCursor::from_bytes(&[0; 25]),
+        Err(Error::InvalidCursor)
+    );
+}
+
+#[test]
+fn candidate_encoding() {
+    let seeds = vec![i32::MIN, -5, 0, 1, 2, 300, 70_000, i32::MAX];
+    let bytes = candidates::encode(&seeds);
+    assert_eq!(candidates::decode(&bytes).unwrap(), seeds);
+    assert_eq!(
+        candidates::decode(&bytes[..bytes.len() - 1]),
+        Err(Error::InvalidCandidates)
+    );
+    let json = candidates::encode_json(&seeds);
+    assert_eq!(candidates::decode_json(&json).unwrap(), seeds);
+    assert_eq!(candidates::decode_json(" [ ] ").unwrap(), Vec::<i32>::new());
+    assert_eq!(
+        candidates::decode_json("[1,a]"),
+        Err(Error::InvalidCandidates)
+    );
+}
+
+#[test]
+fn import_export() {
+    let info = EnchantmentTableInfo::new(15, 5, 20, 30).unwrap();
+    let info2 = EnchantmentTableInfo::new(12, 5, 10, 24).unwrap();
+
+    let mut whole = full_cracker();
+    whole.search_chunk(&[info, info2], 0, 2_000_000).unwrap();
+
+    let mut first = full_cracker();
+    first.search_chunk(&[info, info2], 0, 1_000_000).unwrap();
+    let mut second = full_cracker();
+    second
+        .search_chunk(&[info, info2], 1_000_000, 1_000_000)
+        .unwrap();
+
+    let mut merged = full_cracker();
+    merged
+        .import_candidates(&second.export_candidates())
+        .unwrap();
+    merged
+        .import_candidates_json(&first.export_candidates_json())
+        .unwrap();
+    assert_eq!(merged.export_candidates(), whole.export_candidates());
+}
+
+#[test]
+fn merge_histories() {
+    let seed = KNOWN_SEED;
+    let input = EnchantmentTableInfo::new(8, 2, 10, 16).unwrap();
+    let typo = EnchantmentTableInfo::new(6, 3, 9, 13).unwrap();
+    let cracker = || {
+        let mut cracker = full_cracker();
+        cracker.set_tolerance(1);
+        cracker
+    };
+    let mut whole = cracker_near_known_seed();
+    whole.set_tolerance(1);
+    whole.add_input(input);
+    whole.add_input(typo);
+
+    // the first shard has the seed but never saw the typo
+    let mut first = cracker();
+    first
+        .search_chunk(&known_inputs(), seed - 2_000_000, 2_000_001)
+        .unwrap();
+    first.add_input(input);
+    let mut second = cracker();
+    second
+        .search_chunk(&known_inputs(), seed + 1, 1_999_999)
+        .unwrap();
+    second.add_input(input);
+    second.add_input(typo);
+    assert!(first.can_merge(&second));
+    first.merge(&mut second).unwrap();
+    assert_eq!(second.possible_seeds(), 0);
+    assert_eq!(first.observations(), whole.observations());
+    assert_eq!(first.export_candidates(), whole.export_candidates());
+    assert_eq!(first.mismatches(seed), vec![1]);
+    assert_eq!(
+        first.seeds_with_mismatches(1),
+        whole.seeds_with_mismatches(1)
+    );
+    first.set_tolerance(0);
+    whole.set_tolerance(0);
+    assert!(first.undo() && whole.undo());
+    assert_eq!(first.export_candidates(), whole.export_candidates());
+
+    // nothing moves when the histories differ
+    let mut other = cracker();
+    other.search_chunk(&known_inputs(), seed, 1).unwrap();
+    other.add_input(typo);
+    assert!(!first.can_merge(&other));
+    assert_eq!(first.merge(&mut other), Err(Error::DifferentHistories));
+    assert!(other.contains(seed));
+}
+
+#[cfg(not(feature = "parallel"))]
+#[test]
+fn merge_shards() {
+    let info = EnchantmentTableInfo::new(15, 5, 20, 30).unwrap();
+    let info2 = EnchantmentTableInfo::new(12, 5, 10, 24).unwrap();
+    // seed that matches both, the one from the web tests
+    let seed = -329083225;
+
+    let shards: Vec<Cracker> = (0..4)
+        .map(|id| {
+            let mut cracker = Cracker::new(id, 4);
+            // only look around the known seed so the test stays fast
+            if cracker
+                .cursor(&[info, info2])
+                .unwrap()
+                .remaining()
+                .contains(&(seed as i64))
+            {
+                cracker
+                    .search_chunk(&[info, info2], seed - 500_000, 1_000_000)
+                    .unwrap();
+            }
+            cracker
+        })
+        .collect();
+    let total: usize = shards.iter().map(|x| x.possible_seeds()).sum();
+
+    let mut merged = Cracker::from_shards(shards).unwrap();
+    assert_eq!(merged.possible_seeds(), total);
+    assert!(merged.contains(seed));
+    assert_eq!(
+        merged.cursor(&[info, info2]).unwrap().remaining(),
+        search::SEED_SPACE
+    );
+
+    for (shelves, slot1, slot2, slot3) in [
+        (8, 2, 10, 16),
+        (6, 3, 9, 12),
+        (4, 1, 9, 8),
+        (3, 1, 9, 6),
+        (7, 2, 13, 14),
+        (14, 6, 12, 28),
+    ] {
+        merged.add_input(EnchantmentTableInfo::new(shelves, slot1, slot2, slot3).unwrap());
+    }
+    assert_eq!(merged.seed(), Ok(seed));
+
+    assert_eq!(
+        Cracker::from_shards(Vec::new()).err(),
+        Some(Error::NoShards)
+    );
+}
+
+#[test]
+fn candidate_set_switches_storage() {
+    let mut set = candidates::CandidateSet::new(0..6400);
+    for seed in (0..6400).step_by(100) {
+        set.push(seed);
+    }
+    set.rebalance();
+    assert!(!set.is_dense());
+
+    set.extend((0..6400).step_by(10));
+    assert!(set.is_dense());
+    assert_eq!(set.len(), 640);
+    assert!(set.contains(6390) && !set.contains(6391));
+    assert_eq!(set.iter().take(3).collect::<Vec<_>>(), vec![0, 10, 20]);
+    let dense_bytes = set.encode();
+
+    set.retain(|x| x % 1000 == 0);
+    assert!(!set.is_dense());
+    assert_eq!(
+        set.iter().collect::<Vec<_>>(),
+        vec![0, 1000, 2000, 3000, 4000, 5000, 6000]
+    );
+
+    // seeds outside of the range make it grow
+    set.extend([-5, 7000]);
+    assert_eq!(set.range(), -5..7001);
+    assert!(set.contains(-5) && set.contains(7000));
+
+    let mut decoded = candidates::CandidateSet::new(0..6400);
+    decoded.extend(candidates::decode(&dense_bytes).unwrap());
+    assert_eq!(decoded.len(), 640);
+
+    // unsorted seeds with repeats, after or in between the ones already there
+    let mut set = candidates::CandidateSet::new(0..1_000_000);
+    set.extend([30, 10, 20, 10]);
+    set.extend([50, 40]);
+    set.extend([25, 40, 5]);
+    assert_eq!(
+        set.iter().collect::<Vec<_>>(),
+        vec![5, 10, 20, 25, 30, 40, 50]
+    );
+}
+
+#[test]
+fn candidate_paging() {
+    let mut set = candidates::CandidateSet::new(0..6400);
+    set.extend((0..6400).step_by(7));
+    assert!(set.is_dense());
+    let all: Vec<i32> = set.iter().collect();
+    for n in [
+        0,
+        1,
+        9,
+        10,
+        63,
+        64,
+        500,
+        all.len() - 1,
+        all.len(),
+        all.len() + 3,
+    ] {
+        assert_eq!(set.iter().nth(n), all.get(n).copied(), "{}", n);
+        let mut iter = set.iter();
+        iter.nth(n);
+        assert_eq!(iter.collect::<Vec<_>>(), all.get(n + 1..).unwrap_or(&[]));
+    }
+
+    let mut cracker = full_cracker();
+    cracker
+        .search_chunk(
+            &[EnchantmentTableInfo::new(15, 5, 20, 30).unwrap()],
+            -329083225 - 50_000,
+            100_000,
+        )
+        .unwrap();
+    let seeds: Vec<i32> = cracker.candidates().collect();
+    assert_eq!(seeds.len(), cracker.possible_seeds());
+    assert_eq!(cracker.candidate_page(10, 5), seeds[10..15]);
+    assert_eq!(
+        cracker.candidate_page(seeds.len() - 2, 5),
+        seeds[seeds.len() - 2..]
+    );
+    assert!(cracker.candidate_page(seeds.len(), 5).is_empty());
+}
+
+#[test]
+fn crack_all_matches_add_input() {
+    let infos: Vec<EnchantmentTableInfo> = [
+        (15, 5, 20, 30),
+        (12, 5, 10, 24),
+        (8, 2, 10, 16),
+        (6, 3, 9, 12),
+    ]
+    .iter()
+    .map(|&(shelves, slot1, slot2, slot3)| {
+        EnchantmentTableInfo::new(shelves, slot1, slot2, slot3).unwrap()
+    })
+    .collect();
+    let seed = KNOWN_SEED;
+
+    let mut filtered = cracker_near_known_seed();
+    for &info in infos[2..].iter() {
+        filtered.add_input(info);
+    }
+
+    let mut lazy = full_cracker();
+    let mut cursor =
+        search::SearchCursor::with_range(&infos, seed as i64 - 2_000_000..seed as i64 + 2_000_000)
+            .unwrap();
+    lazy.resume(&mut cursor, 4_000_000);
+    assert_eq!(lazy.export_candidates(), filtered.export_candidates());
+    assert!(lazy.contains(seed));
+
+    assert_eq!(full_cracker().crack_all(&[]), Err(Error::NoInputs));
+}
+
+#[test]
+fn bit_constraints_match_brute_force() {
+    let sets: [&[(i32, i32, i32, i32)]; 7] = [
+        &[(15, 5, 20, 30)],
+        &[(15, 5, 20, 30), (7, 2, 13, 14)],
+        &[(12, 5, 10, 24), (3, 1, 9, 6)],
+        &[(0, 1, 1, 1), (1, 1, 3, 3)],
+        &[(15, 5, 20, 30), (0, 9, 9, 9)],
+        &[(15, 0, 20, 30), (12, 0, 10, 0)],
+        &[(7, 2, 0, 14), (0, 0, 0, 0)],
+    ];
+    let mut rng: utils::SimpleRandom = Default::default();
+    for infos in sets {
+        for range in [
+            -3_000_017..1_000_003,
+            -329083225 - 1_000_000..-329083225 + 10,
+        ] {
+            let mut walked = Vec::new();
+            utils::BitConstraints::new(infos).for_each(range.clone(), |x| walked.push(x));
+            walked.sort();
+            let before = walked.len();
+            walked.dedup();
+            assert_eq!(before, walked.len());
+            assert!(walked.iter().all(|&x| range.contains(&(x as i64))));
+            walked.retain(|&x| infos.iter().all(|&info| rng.verify_seed(x, info)));
+
+            let brute: Vec<i32> = range
+                .clone()
+                .map(|x| x as i32)
+                .filter(|&x| infos.iter().all(|&info| rng.verify_seed(x, info)))
+                .collect();
+            assert_eq!(walked, brute);
+        }
+    }
+    assert_eq!(
+        utils::BitConstraints::new(&[(0, 9, 9, 9)]).selectivity(),
+        0.0
+    );
+    assert!(utils::BitConstraints::new(&[(15, 5, 20, 30)]).selectivity() < 0.2);
+}
+
+#[test]
+fn batch_matches_verify_seed() {
+    // these seeds go through java's rejection loop in slot 1 with 12 bookshelves
+    let rejected = [41066301, 1164185337, -2069909067, -944692879, 493009194];
+    let infos = [
+        (12, 4, 13, 24),
+        (12, 5, 10, 24),
+        (15, 5, 20, 30),
+        (7, 2, 13, 14),
+        (0, 1, 1, 1),
+        (3, 1, 9, 6),
+        (12, 0, 13, 24),
+        (12, 4, 0, 0),
+        (15, 0, 0, 0),
+    ];
+    let mut seeds: Vec<i32> = rejected.to_vec();
+    seeds.extend((-329083225 - 40..-329083225 + 40).chain(i32::MAX - 20..=i32::MAX));
+    let mut rng: utils::SimpleRandom = Default::default();
+    for backend in batch::Backend::available() {
+        for chunk in seeds.chunks(batch::MAX_BATCH - 3) {
+            for &info in infos.iter() {
+                let expected = chunk
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &x)| rng.verify_seed(x, info))
+                    .fold(0, |mask, (i, _)| mask | 1 << i);
+                assert_eq!(
+                    batch::verify_seeds_on(backend, chunk, &[info]),
+                    expected,
+                    "{:?} {:?}",
+                    backend,
+                    info
+                );
+            }
+        }
+    }
+    assert!(rng.verify_seed(41066301, infos[0]));
+    assert_eq!(
+        utils::SimpleRandom::verify_seeds(&rejected[..1], &infos[..1]),
+        1
+    );
+    assert_eq!(
+        utils::SimpleRandom::verify_seeds(&[-329083225], &infos[1..3]),
+        1
+    );
+    // only the first MAX_BATCH seeds count
+    let many = vec![-329083225; batch::MAX_BATCH + 4];
+    assert_eq!(
+        batch::verify_seeds(&many, &infos[1..3]),
+        (1 << batch::MAX_BATCH) - 1
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn own_thread_pool() {
+    let infos = [
+        EnchantmentTableInfo::new(15, 5, 20, 30).unwrap(),
+        EnchantmentTableInfo::new(12, 5, 10, 24).unwrap(),
+    ];
+    let seed = -329083225;
+
+    let mut pooled = Cracker::with_threads(3).unwrap();
+    assert_eq!(pooled.threads(), 3);
+    pooled
+        .search_chunk(&infos, seed - 3_000_000, 6_000_000)
+        .unwrap();
+    assert!(pooled.contains(seed));
+
+    let mut global = Cracker::new();
+    global
+        .search_chunk(&infos, seed - 3_000_000, 6_000_000)
+        .unwrap();
+    assert_eq!(pooled.export_candidates(), global.export_candidates());
+}
@@ -1,11 +1,12 @@
 //! Test suite for the Web and headless browsers.
-#![cfg(target_arch = "wasm32")]
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
 
 extern crate libenchcrack;
 use libenchcrack::manipulation::*;
+use libenchcrack::random::JavaRandom;
 use libenchcrack::utils::SimpleRandom;
-use libenchcrack::*;
-use std::num::Wrapping;
+use libenchcrack::wasm::*;
+use libenchcrack::Error;
 use std::panic;
 use wasm_bindgen_test::*;
 use web_sys::console;
@@ -43,25 +44,51 @@ fn rng_seed() {
 #[wasm_bindgen_test]
 fn rng_next_int() {
     let mut rng: SimpleRandom = Default::default();
+    // 31 bits is what `next_int_bound` starts from
     rng.set_seed(0);
-    assert_eq!(rng.next_int(), 1569741360);
+    assert_eq!(rng.next(31), 1569741360);
     rng.set_seed(150123);
-    assert_eq!(rng.next_int(), 286134746);
+    assert_eq!(rng.next(31), 286134746);
     rng.set_seed(-500);
-    assert_eq!(rng.next_int(), 518875706);
+    assert_eq!(rng.next(31), 518875706);
+    rng.set_seed(0);
+    assert_eq!(rng.next_int(), -1155484576);
 }
 
 #[wasm_bindgen_test]
 fn rng_next_int_bound() {
     let mut rng: SimpleRandom = Default::default();
     rng.set_seed(0);
-    assert_eq!(rng.next_int_bound(Wrapping(8)), 5);
-    assert_eq!(rng.next_int_bound(Wrapping(8)), 6);
+    assert_eq!(rng.next_int_bound(8), 5);
+    assert_eq!(rng.next_int_bound(8), 6);
     rng.set_seed(1949457528);
-    assert_eq!(rng.next_int_bound(Wrapping(8)), 3);
-    assert_eq!(rng.next_int_bound(Wrapping(5)), 0);
+    assert_eq!(rng.next_int_bound(8), 3);
+    assert_eq!(rng.next_int_bound(5), 0);
     rng.set_seed(-500);
-    assert_eq!(rng.next_int_bound(Wrapping(15)), 11);
+    assert_eq!(rng.next_int_bound(15), 11);
+}
+
+#[wasm_bindgen_test]
+fn batch_verify() {
+    let mut rng: SimpleRandom = Default::default();
+    let seeds: Vec<i32> = (-329083225 - 40..-329083225 + 40)
+        .chain([41066301])
+        .collect();
+    for info in [
+        (15, 5, 20, 30),
+        (12, 5, 10, 24),
+        (12, 4, 13, 24),
+        (0, 1, 1, 1),
+    ] {
+        for chunk in seeds.chunks(libenchcrack::batch::MAX_BATCH) {
+            let expected = chunk
+                .iter()
+                .enumerate()
+                .filter(|&(_, &x)| rng.verify_seed(x, info))
+                .fold(0, |mask, (i, _)| mask | 1 << i);
+            assert_eq!(libenchcrack::batch::verify_seeds(chunk, &[info]), expected);
+        }
+    }
 }
 
 /*#[wasm_bindgen_test]
@@ -108,11 +135,36 @@ fn manipulator() {
     for ench in enchs.iter() {
         man.update_item(item, ench);
     }
-    let v = man
+    let result = man
         .simulate(item, 15, 999, Version::V1_16)
-        .expect("Simulation Failed")
-        .to_vec();
-    assert_eq!(v, vec!(57, 3, 15));
+        .expect("Simulation Failed");
+    assert_eq!(result.kind(), SimulationKind::AfterThrows);
+    assert_eq!(
+        (result.times_needed(), result.slot(), result.shelves()),
+        (57, Some(3), Some(15))
+    );
+}
+
+#[wasm_bindgen_test]
+fn xp_seed_gaps() {
+    let seeds = vec![2893231007, 2635886329];
+    let man = Manipulator::from_xp_seeds(seeds.clone(), vec![1]).expect("Wrong seeds");
+    assert_eq!(
+        man.player_seed().to_vec(),
+        vec![0x2e, 0x3d, 0xf9, 0x6e, 0x1c, 0x9d]
+    );
+    assert_eq!(
+        Manipulator::from_xp_seeds(seeds.clone(), vec![]).err(),
+        Some(Error::WrongGapCount { seeds: 2, gaps: 0 })
+    );
+    assert_eq!(
+        Manipulator::from_xp_seeds(seeds, vec![1, 1]).err(),
+        Some(Error::WrongGapCount { seeds: 2, gaps: 2 })
+    );
+    assert_eq!(
+        Manipulator::from_xp_seeds(vec![], vec![]).err(),
+        Some(Error::NoXpSeeds)
+    );
 }
 
 /*#[wasm_bindgen_test]